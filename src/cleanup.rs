@@ -0,0 +1,215 @@
+use crate::database::Episode;
+use crate::files::{Cli, TransferMode};
+use colored::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use wildmatch::WildMatch;
+
+/// Default patterns of the file names treated as junk by the --cleanup flag, matched without regard to case.
+///
+/// Patterns ending with `/` match the names of folders, every file of which is junk.
+pub const DEFAULT_JUNK: [&str; 12] = [
+    "*.nfo",
+    "*.jpg",
+    "*.jpeg",
+    "*.png",
+    "*.sfv",
+    "*.srr",
+    "*.url",
+    "*-sample.*",
+    "*.sample.*",
+    "Sample/",
+    "Thumbs.db",
+    ".DS_Store",
+];
+
+/// An input directory that will be removed by [`clean_up()`], along with the junk files left in it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cleanup {
+    /// The input directory.
+    pub dir: PathBuf,
+    /// The junk files that will be removed along with the directory, including the ones in its subdirectories.
+    pub junk: Vec<PathBuf>,
+}
+
+/// Returns the directories the --cleanup flag applies to, which are the input paths, or the directories of the episodes when a plan is executed with the --plan-in flag.
+pub fn cleanup_dirs(args: &Cli, episodes: &[Episode]) -> Vec<PathBuf> {
+    if !args.input_paths.is_empty() {
+        return args.input_paths.clone();
+    }
+    let mut dirs = Vec::new();
+    for episode in episodes {
+        if let Some(parent) = Path::new(&episode.old_path).parent() {
+            if !dirs.iter().any(|x: &PathBuf| x == parent) {
+                dirs.push(parent.to_path_buf());
+            }
+        }
+    }
+    dirs
+}
+
+/// The patterns of the --junk flag, compiled by [`Junk::new()`].
+struct Junk {
+    /// Patterns matching the names of junk files.
+    files: Vec<WildMatch>,
+    /// Patterns matching the names of folders holding only junk, given with a trailing `/`.
+    dirs: Vec<WildMatch>,
+    /// The extensions of the --extensions flag, whose files are never junk.
+    extensions: Vec<String>,
+}
+
+impl Junk {
+    /// Compiles the patterns of the --junk flag to match names without regard to case.
+    fn new(args: &Cli) -> Junk {
+        let (dirs, files): (Vec<_>, Vec<_>) = args.junk.iter().partition(|x| x.ends_with('/'));
+        Junk {
+            files: files
+                .iter()
+                .map(|x| WildMatch::new_case_insensitive(x))
+                .collect(),
+            dirs: dirs
+                .iter()
+                .map(|x| WildMatch::new_case_insensitive(x.trim_end_matches('/')))
+                .collect(),
+            extensions: args.extensions.clone(),
+        }
+    }
+
+    /// True if the file at the given path is junk, either because it is in a junk folder or because its name matches a pattern, and its extension is not one of the --extensions flag.
+    fn is_junk(&self, path: &Path, in_junk_dir: bool) -> bool {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let media = path.extension().is_some_and(|e| {
+            self.extensions
+                .iter()
+                .any(|x| e.eq_ignore_ascii_case(x.trim_start_matches('.')))
+        });
+        !media && (in_junk_dir || self.files.iter().any(|x| x.matches(&name)))
+    }
+}
+
+/// Returns every junk file under the given directory, or [`None`] if there is any file that is not junk.
+///
+/// Files in the leaving set are ignored, since they will be moved out of the directory before it is removed.
+fn junk_files(
+    dir: &Path,
+    leaving: &HashSet<PathBuf>,
+    junk: &Junk,
+    in_junk_dir: bool,
+) -> std::io::Result<Option<Vec<PathBuf>>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            let junk_dir = in_junk_dir
+                || junk
+                    .dirs
+                    .iter()
+                    .any(|x| x.matches(&entry.file_name().to_string_lossy()));
+            match junk_files(&path, leaving, junk, junk_dir)? {
+                Some(found) => files.extend(found),
+                None => return Ok(None),
+            }
+        } else if leaving.contains(&path) {
+            continue;
+        } else if junk.is_junk(&path, in_junk_dir) {
+            files.push(path);
+        } else {
+            return Ok(None);
+        }
+    }
+    Ok(Some(files))
+}
+
+/// Returns the directories that the --cleanup flag will remove once the given episodes are transferred, for the preview.
+///
+/// A directory is removed if, once the files of the episodes are moved out of it, it is empty or only holds files whose names match one of the patterns given with the --junk flag, or that are in a folder matching one of the patterns ending with `/`.
+/// Files with one of the extensions of the --extensions flag are never junk, so a directory holding one is left alone.
+/// With a [`TransferMode`] other than [`TransferMode::Move`], the files of the episodes stay, so a directory is only removed if it only held junk to begin with.
+/// Directories that hold the path of a library or any new path are never removed.
+///
+/// # Example
+/// ```
+/// for cleanup in plan_cleanup(&cleanup_dirs(&args, &episodes), &episodes, &args) {
+///     println!("{} will be removed", cleanup.dir.display());
+/// }
+/// ```
+pub fn plan_cleanup(dirs: &[PathBuf], episodes: &[Episode], args: &Cli) -> Vec<Cleanup> {
+    let junk = Junk::new(args);
+    let leaving = match args.transfer_mode() {
+        TransferMode::Move => episodes
+            .iter()
+            .map(|x| PathBuf::from(&x.old_path))
+            .collect(),
+        _ => HashSet::new(),
+    };
+    dirs.iter()
+        .filter(|dir| {
+            !episodes
+                .iter()
+                .any(|x| Path::new(&x.new_path).starts_with(dir))
+                && !args.libraries.iter().any(|x| x.path.starts_with(dir))
+        })
+        .filter_map(|dir| match junk_files(dir, &leaving, &junk, false) {
+            Ok(Some(junk)) => Some(Cleanup {
+                dir: dir.clone(),
+                junk,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the directories that will be removed as shown in the preview, one per line along with the amount of junk files in them, or an empty string if no directory will be removed.
+pub fn cleanup_report(cleanups: &[Cleanup]) -> String {
+    let mut report = String::new();
+    if !cleanups.is_empty() {
+        report += "The following input directories will be removed once the files are renamed:\n";
+    }
+    for cleanup in cleanups {
+        report += &format!(
+            "  {} ({} junk files)\n",
+            cleanup.dir.display().to_string().yellow(),
+            cleanup.junk.len()
+        );
+    }
+    report
+}
+
+/// Removes the directories returned by [`plan_cleanup()`] along with the junk files in them, returning the directories that were removed.
+///
+/// Every directory is checked again before removing it, and is left untouched if a file that is not junk showed up in it since the preview.
+///
+/// # Errors
+/// If a junk file or directory cannot be removed.
+///
+/// # Example
+/// ```
+/// let removed = clean_up(&plan_cleanup(&cleanup_dirs(&args, &episodes), &episodes, &args), &args)?;
+/// ```
+pub fn clean_up(cleanups: &[Cleanup], args: &Cli) -> anyhow::Result<Vec<PathBuf>> {
+    let junk = Junk::new(args);
+    let mut removed = Vec::new();
+    for cleanup in cleanups {
+        let Some(junk) = junk_files(&cleanup.dir, &HashSet::new(), &junk, false)? else {
+            continue;
+        };
+        for file in junk {
+            std::fs::remove_file(file)?;
+        }
+        remove_empty_dirs(&cleanup.dir)?;
+        removed.push(cleanup.dir.clone());
+    }
+    Ok(removed)
+}
+
+/// Removes the given directory after removing every subdirectory in it, all of which must be empty of files.
+fn remove_empty_dirs(dir: &Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            remove_empty_dirs(&entry.path())?;
+        }
+    }
+    std::fs::remove_dir(dir)
+}
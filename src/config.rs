@@ -0,0 +1,211 @@
+use crate::cleanup::DEFAULT_JUNK;
+use crate::files::{Cli, TransferMode};
+use crate::library::Library;
+use crate::rules::load_rules;
+use crate::validate::{parse_template, valid_paths};
+use crate::watch::WatchFolder;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// How colors are used in the output, given with the --color flag or the `color` setting of the config file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Use colors unless the `NO_COLOR` or `CLICOLOR` environment variables say otherwise.
+    #[default]
+    Auto,
+    /// Always use colors.
+    Always,
+    /// Never use colors.
+    Never,
+}
+
+/// The settings of the config file, every one of which is optional.
+///
+/// # Example
+/// ```toml
+/// output_path = "~/Media/TV Shows"
+/// template = "{series} - S{season}E{episode}"
+/// padding = 2
+/// transfer = "copy"
+/// extensions = ["mkv", "mp4"]
+/// color = "never"
+/// junk = ["*.nfo", "*.jpg"]
+///
+/// [libraries.Anime]
+/// path = "/mnt/anime"
+/// template = "{series} - {episode}"
+///
+/// [[watch]]
+/// path = "~/Downloads/Anime"
+/// library = "Anime"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Output path used when the -o flag is not given. A leading `~` is replaced with the home directory.
+    pub output_path: Option<String>,
+    /// Naming template used when the --template flag is not given.
+    pub template: Option<String>,
+    /// Padding of the season and episode numbers used when the --padding flag is not given.
+    pub padding: Option<usize>,
+    /// [`TransferMode`] used when the -t flag is not given.
+    pub transfer: Option<TransferMode>,
+    /// Extensions of the files listed in the input directories, used when the --extensions flag is not given.
+    pub extensions: Option<Vec<String>>,
+    /// [`ColorMode`] used when the --color flag is not given.
+    pub color: Option<ColorMode>,
+    /// Patterns of the junk file names used by the --cleanup flag when the --junk flag is not given.
+    pub junk: Option<Vec<String>>,
+    /// The named libraries the input directories can be renamed to, chosen with the --library flag or when prompted.
+    #[serde(default)]
+    pub libraries: BTreeMap<String, LibraryConfig>,
+    /// The drop folders watched by `rplexfmtr watch` when no folders are given to it.
+    #[serde(default)]
+    pub watch: Vec<WatchConfig>,
+}
+
+/// The settings of a named library in the config file, given as a `[libraries.<name>]` table.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LibraryConfig {
+    /// Root folder of the library. A leading `~` is replaced with the home directory.
+    pub path: String,
+    /// Naming template of the library, the one of the --template flag by default.
+    pub template: Option<String>,
+    /// Padding of the season and episode numbers of the library, the one of the --padding flag by default.
+    pub padding: Option<usize>,
+}
+
+/// The settings of a drop folder in the config file, given as a `[[watch]]` table.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchConfig {
+    /// The folder to watch. A leading `~` is replaced with the home directory.
+    pub path: String,
+    /// Name of the library the files of the folder are renamed to, the only library by default.
+    pub library: Option<String>,
+    /// Series every file of the folder belongs to, guessed from the name of each file by default.
+    pub series: Option<String>,
+}
+
+/// Replaces a leading `~/` in the given path with the home directory of the user.
+fn expand_home(path: String) -> String {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => path,
+    }
+}
+
+/// Returns the path of the config file, which is the one given with the --config flag or the `RPLEXFMTR_CONFIG` environment variable, else `rplexfmtr/config.toml` inside of the config directory of the user.
+///
+/// The config directory follows the `XDG_CONFIG_HOME` environment variable on Linux, which is `~/.config` by default.
+pub fn config_path(args: &Cli) -> Option<PathBuf> {
+    args.config
+        .clone()
+        .or_else(|| dirs::config_dir().map(|x| x.join("rplexfmtr").join("config.toml")))
+}
+
+/// Reads the config file at the given path.
+///
+/// A missing file is treated as an empty config, unless it was given explicitly with the --config flag.
+///
+/// # Errors
+/// If the file cannot be read, is not valid TOML or has a setting that does not exist.
+///
+/// # Example
+/// ```
+/// let config = read_config(Path::new("/home/user/.config/rplexfmtr/config.toml"), false)?;
+/// ```
+pub fn read_config(path: &Path, required: bool) -> anyhow::Result<Config> {
+    if !required && !path.exists() {
+        return Ok(Config::default());
+    }
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Could not read the config file {}: {e}", path.display()))?;
+    toml::from_str(&contents)
+        .map_err(|e| anyhow::anyhow!("Invalid config file {}: {e}", path.display()))
+}
+
+/// Loads the config file given by [`config_path()`] and merges it into the parsed command line arguments.
+///
+/// Every setting is only used if the matching flag was not given on the command line or through its environment variable, so flags always take precedence over the config file.
+/// Afterwards, the settings that were given by neither are set to their defaults and the color setting is applied to [`colored`].
+/// Finally, the libraries of the config file are added to [`Cli::libraries`], followed by the output path as an unnamed [`Library`] if there is one, the drop folders are added to [`Cli::watch_folders`] and the rules file is loaded into [`Cli::rules`] with [`load_rules()`].
+///
+/// # Errors
+/// If the config file cannot be read with [`read_config()`] or has an invalid template, or if the rules file cannot be loaded with [`load_rules()`].
+///
+/// # Exits
+/// Like [`valid_paths()`], exits with a status code of `1` if the output path or the path of a library or drop folder in the config file is not a directory.
+///
+/// # Example
+/// ```
+/// let mut args = Cli::parse();
+/// load_config(&mut args)?;
+/// ```
+pub fn load_config(args: &mut Cli) -> anyhow::Result<()> {
+    let config = match config_path(args) {
+        Some(path) => read_config(&path, args.config.is_some())?,
+        None => Config::default(),
+    };
+    if args.output_path.is_none() {
+        if let Some(output_path) = config.output_path {
+            args.output_path = Some(valid_paths(&expand_home(output_path))?);
+        }
+    }
+    if args.template.is_none() {
+        args.template = config
+            .template
+            .map(|x| parse_template(&x))
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("Invalid template in the config file: {e}"))?;
+    }
+    args.padding = args.padding.or(config.padding);
+    args.transfer = args.transfer.or(config.transfer);
+    if args.extensions.is_empty() {
+        args.extensions = config.extensions.unwrap_or_default();
+    }
+    args.color = args.color.or(config.color);
+    if args.junk.is_empty() {
+        args.junk = config
+            .junk
+            .unwrap_or_else(|| DEFAULT_JUNK.map(String::from).to_vec());
+    }
+    for (name, library) in config.libraries {
+        let template = match library.template {
+            Some(template) => parse_template(&template)
+                .map_err(|e| anyhow::anyhow!("Invalid template for the {name} library: {e}"))?,
+            None => args.template().to_owned(),
+        };
+        args.libraries.push(Library {
+            name: Some(name),
+            path: valid_paths(&expand_home(library.path))?,
+            template,
+            padding: library.padding.unwrap_or(args.padding()),
+        });
+    }
+    if let Some(output_path) = &args.output_path {
+        args.libraries.push(Library {
+            name: None,
+            path: output_path.clone(),
+            template: args.template().to_owned(),
+            padding: args.padding(),
+        });
+    }
+    for folder in config.watch {
+        args.watch_folders.push(WatchFolder {
+            path: std::fs::canonicalize(valid_paths(&expand_home(folder.path))?)?,
+            library: folder.library,
+            series: folder.series,
+        });
+    }
+    args.rules = load_rules(args)?;
+    match args.color.unwrap_or_default() {
+        ColorMode::Auto => {}
+        ColorMode::Always => colored::control::set_override(true),
+        ColorMode::Never => colored::control::set_override(false),
+    }
+    Ok(())
+}
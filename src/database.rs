@@ -1,4 +1,5 @@
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use tabled::Tabled;
 
 use sqlx::{migrate::MigrateDatabase, sqlite::SqliteQueryResult, FromRow, Sqlite, SqlitePool};
@@ -30,6 +31,7 @@ pub struct Show {
 /// Struct to hold an episode entry from the database.
 ///
 /// This is used in conjunction with the [`sqlx::query_as()`] function to get the episode entries from a database query. Derives [`sqlx::FromRow`] trait in order to capture an episode entry from the database. Derives [`tabled::Tabled`] so that a vector of [`Episode`] can be easily converted to a table.
+/// Derives [`serde::Serialize`] and [`serde::Deserialize`] so that the episodes can be saved to and loaded from a plan file by [`crate::plan`].
 ///
 /// # Panics
 ///
@@ -38,24 +40,59 @@ pub struct Show {
 /// # Examples
 /// ```
 ///     # let db = rplexfmtr::setup_database().await;
-///     # rplexfmtr::insert_episode(&db,"Show",1,1,"/home/user/show1.mkv","/home/user/output/show S1E1.mkv");
+///     # rplexfmtr::insert_episode(&db,"Show",1,1,None,"/home/user/show1.mkv","/home/user/output/show S1E1.mkv");
 ///     sqlx::query_as::<_, Episode>(
-///     "SELECT DISTINCT series_name, season, episode, old_path, new_path FROM episodes WHERE series_name = ? ORDER BY LENGTH(series_name), series_name, LENGTH(season), season, LENGTH(old_path), old_path;");
+///     "SELECT DISTINCT series_name, season, episode, title, old_path, new_path FROM episodes WHERE series_name = ? ORDER BY LENGTH(series_name), series_name, LENGTH(season), season, LENGTH(old_path), old_path;");
 /// ```
-#[derive(Clone, FromRow, Debug, Tabled)]
+#[derive(Clone, FromRow, Debug, Tabled, Serialize, Deserialize)]
 pub struct Episode {
     /// Holds the series name for [`select_all_episodes()`].
     pub series_name: String,
     /// Holds the season number for [`select_all_episodes()`].
     pub season: u32,
     /// Holds the episode number for [`select_all_episodes()`].
-    episode: u32,
+    pub episode: u32,
+    /// Holds the title of the episode for [`select_all_episodes()`], if one was given, as sanitized by [`crate::titles::sanitize_title()`].
+    ///
+    /// Defaults to [`None`] when the column is not selected, such as for the runs kept by [`crate::history`].
+    #[sqlx(default)]
+    #[tabled(display_with = "display_title")]
+    pub title: Option<String>,
     /// Holds the current path for the file for the current episode for [`select_all_episodes()`].
     pub old_path: String,
     /// Holds the output path for the file for the current episode for [`select_all_episodes()`].
     pub new_path: String,
 }
 
+impl Episode {
+    /// Returns the episode with its old_path and new_path made absolute against the current directory, so that they still point to the same files when read from another directory.
+    ///
+    /// # Errors
+    /// If the current directory cannot be found.
+    ///
+    /// # Example
+    /// ```
+    /// let episode = episode.absolute()?;
+    /// assert!(Path::new(&episode.old_path).is_absolute());
+    /// ```
+    pub fn absolute(&self) -> anyhow::Result<Episode> {
+        Ok(Episode {
+            old_path: std::path::absolute(&self.old_path)?
+                .to_string_lossy()
+                .into_owned(),
+            new_path: std::path::absolute(&self.new_path)?
+                .to_string_lossy()
+                .into_owned(),
+            ..self.clone()
+        })
+    }
+}
+
+/// Shows the title of an [`Episode`] in a table, leaving the cell empty if it has none.
+fn display_title(title: &Option<String>) -> String {
+    title.clone().unwrap_or_default()
+}
+
 /// Setup the database connection and tables and returns the database connection.
 ///
 /// Should be used before any database operation is performed since it returns the executor for the in memory database.
@@ -75,7 +112,7 @@ pub async fn setup_database(url: &str) -> anyhow::Result<sqlx::Pool<Sqlite>> {
 
     let db = SqlitePool::connect(url).await?;
 
-    sqlx::query("CREATE TABLE episodes (series_name TEXT, season INTEGER NOT NULL, episode INTEGER NOT NULL, old_path TEXT NOT NULL UNIQUE, new_path TEXT NOT NULL UNIQUE);")
+    sqlx::query("CREATE TABLE episodes (series_name TEXT, season INTEGER NOT NULL, episode INTEGER NOT NULL, title TEXT, old_path TEXT NOT NULL UNIQUE, new_path TEXT NOT NULL UNIQUE);")
         .execute(&db)
         .await?;
 
@@ -109,8 +146,9 @@ pub async fn select_all_shows(db: &SqlitePool) -> anyhow::Result<Vec<Show>> {
 /// 2. series name
 /// 3. season number
 /// 4. episode number
-/// 5. current file path
-/// 6. output file path
+/// 5. episode title, if any
+/// 6. current file path
+/// 7. output file path
 ///
 /// This will insert the given episode into the episodes table of the database.
 ///
@@ -119,7 +157,7 @@ pub async fn select_all_shows(db: &SqlitePool) -> anyhow::Result<Vec<Show>> {
 ///
 /// # Examples
 /// ```
-/// insert_episode(&db,"Show",1,1,"/home/user/show1.mkv","/home/user/output/show S1E1.mkv");
+/// insert_episode(&db,"Show",1,1,None,"/home/user/show1.mkv","/home/user/output/show S1E1.mkv");
 /// ```
 /// **NOTE:** The series_name should be first verified by [`crate::validate::valid_name()`] to ensure that old_path and new_path are valid.
 pub async fn insert_episode(
@@ -127,14 +165,16 @@ pub async fn insert_episode(
     series_name: &str,
     season: u32,
     episode: u32,
+    title: Option<&str>,
     old_path: PathBuf,
     new_path: PathBuf,
 ) -> anyhow::Result<SqliteQueryResult> {
     Ok(
-        sqlx::query("INSERT INTO episodes (series_name, season, episode, old_path, new_path) VALUES (?,?,?,?,?)")
+        sqlx::query("INSERT INTO episodes (series_name, season, episode, title, old_path, new_path) VALUES (?,?,?,?,?,?)")
             .bind(series_name)
             .bind(season)
             .bind(episode)
+            .bind(title)
             .bind(old_path.as_os_str().to_str().unwrap())
             .bind(new_path.as_os_str().to_str().unwrap())
             .execute(db)
@@ -144,7 +184,7 @@ pub async fn insert_episode(
 
 ///  Given a database connection will return all distinct series_name values from the database given a database connection and a series name.
 ///
-///  This function will return a [`Vec<Episode>`], if the database is empty the return vector will also be empty. The entries are sorted in a natural order.
+///  This function will return a [`Vec<Episode>`], if the database is empty the return vector will also be empty. The entries are sorted by season and episode, and by current file path when several files share an episode, such as a video and its subtitles.
 ///
 ///  # Panics
 ///
@@ -155,15 +195,129 @@ pub async fn insert_episode(
 ///  # let db = setup_database();
 ///  let result = select_all_episodes(&db,"Show");
 ///  ```
-
 pub async fn select_all_episodes(
     db: &SqlitePool,
     series_name: &str,
 ) -> anyhow::Result<Vec<Episode>> {
     Ok(sqlx::query_as::<_, Episode>(
-        "SELECT DISTINCT series_name, season, episode, old_path, new_path FROM episodes WHERE series_name = ? GROUP BY series_name, season, episode ORDER BY LENGTH(series_name), series_name;",
+        "SELECT DISTINCT series_name, season, episode, title, old_path, new_path FROM episodes WHERE series_name = ? ORDER BY season, episode, old_path;",
     )
     .bind(series_name)
     .fetch_all(db)
     .await?)
 }
+
+/// Given a database connection will return every episode entry in the database, grouped by series in the same order as [`select_all_shows()`] and [`select_all_episodes()`].
+///
+/// This is the full set of changes that [`crate::files::move_files()`] will perform. If the database is empty the return vector will also be empty.
+///
+/// # Panics
+///
+/// Panics if given database connection does not contain the episode table created in [`setup_database()`].
+///
+/// # Examples
+/// ```
+/// # let db = setup_database();
+/// let plan = select_plan(&db);
+/// ```
+pub async fn select_plan(db: &SqlitePool) -> anyhow::Result<Vec<Episode>> {
+    let mut entries: Vec<Episode> = Vec::new();
+    for show in select_all_shows(db).await? {
+        entries.append(&mut select_all_episodes(db, &show.series_name).await?);
+    }
+    Ok(entries)
+}
+
+/// Given a database connection will remove every episode entry from the database.
+///
+/// Used when the entries need to be rebuilt from scratch, for example by the terminal user interface every time an answer is edited.
+///
+/// # Panics
+///
+/// Panics if given database connection does not contain the episode table created in [`setup_database()`].
+///
+/// # Examples
+/// ```
+/// # let db = setup_database();
+/// delete_all_episodes(&db);
+/// ```
+pub async fn delete_all_episodes(db: &SqlitePool) -> anyhow::Result<SqliteQueryResult> {
+    Ok(sqlx::query("DELETE FROM episodes;").execute(db).await?)
+}
+
+/// Given a database connection and an input directory, will remove every episode entry whose current file is inside that directory.
+///
+/// Used to discard the answers for a directory when they are revised or the directory is skipped. Only files directly inside the directory are removed, matching the files returned by [`crate::files::get_files()`].
+///
+/// # Panics
+///
+/// Panics if given database connection does not contain the episode table created in [`setup_database()`].
+///
+/// # Examples
+/// ```
+/// # let db = setup_database();
+/// delete_directory_episodes(&db, Path::new("/home/user/show"));
+/// ```
+pub async fn delete_directory_episodes(db: &SqlitePool, directory: &Path) -> anyhow::Result<()> {
+    for episode in select_plan(db).await? {
+        if Path::new(&episode.old_path).parent() == Some(directory) {
+            delete_episode(db, &episode.old_path).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Will update the season, episode number and output path of an episode entry given a:
+/// 1. database connection
+/// 2. current file path of the entry to update
+/// 3. new season number
+/// 4. new episode number
+/// 5. new output file path
+///
+/// The entry is identified by its current file path, which is unique. If no entry has the given current file path, nothing is updated.
+///
+/// # Panics
+/// Will panic if the new output file path is already used by another entry since the new_path column is unique.
+///
+/// # Examples
+/// ```
+/// update_episode(&db,"/home/user/show1.mkv",2,1,"/home/user/output/show/Season 2/show S2E1.mkv");
+/// ```
+pub async fn update_episode(
+    db: &SqlitePool,
+    old_path: &str,
+    season: u32,
+    episode: u32,
+    new_path: PathBuf,
+) -> anyhow::Result<SqliteQueryResult> {
+    Ok(
+        sqlx::query(
+            "UPDATE episodes SET season = ?, episode = ?, new_path = ? WHERE old_path = ?;",
+        )
+        .bind(season)
+        .bind(episode)
+        .bind(new_path.as_os_str().to_str().unwrap())
+        .bind(old_path)
+        .execute(db)
+        .await?,
+    )
+}
+
+/// Given a database connection and the current file path of an episode entry, will remove the entry from the database so that the file is not renamed.
+///
+/// If no entry has the given current file path, nothing is removed.
+///
+/// # Panics
+///
+/// Panics if given database connection does not contain the episode table created in [`setup_database()`].
+///
+/// # Examples
+/// ```
+/// delete_episode(&db,"/home/user/show1.mkv");
+/// ```
+pub async fn delete_episode(db: &SqlitePool, old_path: &str) -> anyhow::Result<SqliteQueryResult> {
+    Ok(sqlx::query("DELETE FROM episodes WHERE old_path = ?;")
+        .bind(old_path)
+        .execute(db)
+        .await?)
+}
@@ -0,0 +1,111 @@
+use crate::database::Episode;
+use crate::files::{Cli, TransferMode};
+use serde::Serialize;
+use std::io::Write;
+
+/// Format of the events streamed by [`crate::files::move_files()`].
+///
+/// Used by the `--events` flag of [`crate::files::Cli`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum EventFormat {
+    /// One JSON object per line.
+    Ndjson,
+}
+
+/// What happened to an episode while the files were transferred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    /// The episode is part of the plan. Sent for every episode before any file is transferred.
+    Planned,
+    /// The file of the episode is about to be transferred.
+    Started,
+    /// The file was moved to its new path.
+    Moved,
+    /// The file was copied to its new path.
+    Copied,
+    /// A hard or symbolic link to the file was created at its new path.
+    Linked,
+    /// The file could not be transferred. The error is included in the event.
+    Failed,
+    /// The file was left untouched since it is already at its new path.
+    Skipped,
+    /// The transfer of the file was reverted because another file failed to be transferred with the --atomic flag.
+    #[serde(rename = "rolledback")]
+    RolledBack,
+}
+
+/// A single event, written as one line of JSON with the fields of the [`Episode`] it belongs to.
+///
+/// # Example
+/// ```json
+/// {"event":"moved","series_name":"Show","season":1,"episode":1,"old_path":"/home/user/show1.mkv","new_path":"/home/user/output/Show/Season 1/Show S1E1.mkv","transfer_mode":"move"}
+/// ```
+#[derive(Debug, Serialize)]
+pub struct Event<'a> {
+    /// What happened to the episode.
+    pub event: EventKind,
+    /// The episode the event belongs to.
+    #[serde(flatten)]
+    pub episode: &'a Episode,
+    /// How the file of the episode is transferred.
+    pub transfer_mode: TransferMode,
+    /// The error that made the transfer fail, only present in [`EventKind::Failed`] events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Destination of the events given by the `--events` and `--events-file` flags.
+///
+/// When no events were asked for, every event is discarded.
+pub struct Events {
+    writer: Option<Box<dyn Write>>,
+    transfer_mode: TransferMode,
+}
+
+impl Events {
+    /// Opens the destination of the events given by the command line, creating or truncating the events file if one was given.
+    ///
+    /// # Example
+    /// ```
+    /// let mut events = Events::new(&args)?;
+    /// ```
+    pub fn new(args: &Cli) -> anyhow::Result<Events> {
+        let writer: Option<Box<dyn Write>> = match (args.events, &args.events_file) {
+            (None, _) => None,
+            (Some(EventFormat::Ndjson), Some(path)) => Some(Box::new(std::io::BufWriter::new(
+                std::fs::File::create(path)?,
+            ))),
+            (Some(EventFormat::Ndjson), None) => Some(Box::new(std::io::stdout())),
+        };
+        Ok(Events {
+            writer,
+            transfer_mode: args.transfer_mode(),
+        })
+    }
+
+    /// Writes an event for the given episode, flushing it right away so that other programs can follow the transfer as it happens.
+    ///
+    /// # Example
+    /// ```
+    /// events.send(EventKind::Started, &episode, None)?;
+    /// ```
+    pub fn send(
+        &mut self,
+        event: EventKind,
+        episode: &Episode,
+        error: Option<String>,
+    ) -> anyhow::Result<()> {
+        if let Some(writer) = self.writer.as_mut() {
+            let event = Event {
+                event,
+                episode,
+                transfer_mode: self.transfer_mode,
+                error,
+            };
+            writeln!(writer, "{}", serde_json::to_string(&event)?)?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
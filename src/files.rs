@@ -1,15 +1,47 @@
 use crate::*;
 use clap::Parser;
 use colored::*;
+use regex::Regex;
 use std::fs::read_dir;
 use std::fs::DirEntry;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Once};
+use tabled::Tabled;
 
-/// Struct for the command line argument parser that allows for multiple input paths (minimum of 1) and a single output path.
+/// Struct for the command line arguments of the rename flow that allows for multiple input paths (minimum of 1) and a single output path.
 ///
-/// This struct uses the [`clap`] crate syntax to have two flags:
+/// The arguments are given either without a command or after the `rename` command, as parsed by [`Rplexfmtr`].
+///
+/// This struct uses the [`clap`] crate syntax to have the following flags:
 /// 1. path: Input paths that contain the media files to be renamed which are verified to be correctly inputted by [`valid_paths()`]. One or more paths can be inputed with a single use of a of the -p flag or each path can be specified by an individual -p flag.
-/// 2. output_path: Output path for the Plex® Media Server formatted media which is verified by [`valid_paths()`]. A single output path can be specified with the -o flag.
+/// 2. output_path: Output path for the Plex® Media Server formatted media which is verified by [`valid_paths()`]. A single output path can be specified with the -o flag. It is not needed if libraries are named in the config file.
+/// 3. order: The [`SelectionOrder`] used to number the selected files, natural by default. Can be specified with the --order flag.
+/// 4. picker: Choose the files with [`crate::picker::pick_files()`] instead of typing a range. Enabled with the --picker flag.
+/// 5. tui: Answer everything in [`crate::tui::run_tui()`] instead of the prompts. Enabled with the --tui flag.
+/// 6. transfer: The [`TransferMode`] used by [`move_files()`], move by default. Can be specified with the -t flag.
+/// 7. plan_out: Path of a JSON or CSV file where the planned changes are saved with [`crate::plan::save_plan()`]. Can be specified with the --plan-out flag.
+/// 8. plan_in: Path of a JSON or CSV plan file loaded with [`crate::plan::load_plan()`] and executed without prompting. Can be specified with the --plan-in flag, in which case the input and output paths are not needed.
+/// 9. emit_script: Path of a shell script written with [`crate::script::emit_scripts()`] instead of executing the changes. Can be specified with the --emit-script flag.
+/// 10. output: The [`OutputFormat`] of the preview, table by default. Can be specified with the --output flag.
+/// 11. events: Stream [`crate::events::Event`]s while the files are transferred by [`move_files()`]. Enabled with the --events flag, and written to standard output unless a file is given with the --events-file flag.
+/// 12. keep_going: Keep transferring the remaining files when one fails, as described in [`move_files()`]. Enabled with the --keep-going flag.
+/// 13. atomic: Revert every change when a file fails to be transferred, as described in [`move_files()`]. Enabled with the --atomic flag, which cannot be used along with --keep-going.
+/// 14. The flags of items 2, 6, 11 to 13 and 15 to 20 can also be given along with a [`Command`] other than [`Command::Rename`], while the rest cannot.
+/// 15. owner and mode: The [`Owner`] and permission bits set on every renamed file with [`set_owner_and_mode()`]. Can be specified with the --owner and --mode flags.
+/// 16. cleanup and junk: Remove the input directories that are left empty or only hold junk files with [`crate::cleanup::clean_up()`] once every file is renamed. Enabled with the --cleanup flag, and the patterns of junk file names can be specified with the --junk flag.
+/// 17. config: Path of the TOML file loaded with [`crate::config::load_config()`], whose settings are used for the flags that are not given. Can be specified with the --config flag.
+/// 18. template and padding: The file name given to the episodes by [`episode_path()`] and the minimum amount of digits of their numbers. Can be specified with the --template and --padding flags.
+/// 19. extensions: The extensions of the files listed by [`get_files()`], every file by default. Can be specified with the --extensions flag.
+/// 20. color: The [`ColorMode`] of the output, auto by default. Can be specified with the --color flag.
+/// 21. library and libraries: The name of the [`Library`] every input directory is renamed to, or one per input directory, as routed by [`route_libraries()`]. Can be specified with the -l flag, while the libraries themselves are read from the config file.
+/// 22. watch_folders: The [`WatchFolder`]s of the config file, watched by [`crate::watch::watch()`] when the [`Command::Watch`] command is given without folders.
+/// 23. rules_file and rules: Path of the TOML file of [`Rule`]s loaded with [`crate::rules::load_rules()`], which match files to their episodes without prompting. Can be specified with the --rules flag.
+/// 24. titles: Ask for an episode list giving the titles of the episodes of every input directory, read with [`crate::titles::parse_titles()`] and used by [`episode_path()`]. Enabled with the --titles flag.
+///
+/// The output path, transfer mode, config, template, padding, extensions, color and rules file can also be given with the `RPLEXFMTR_OUTPUT`, `RPLEXFMTR_TRANSFER`, `RPLEXFMTR_CONFIG`, `RPLEXFMTR_TEMPLATE`, `RPLEXFMTR_PADDING`, `RPLEXFMTR_EXTENSIONS`, `RPLEXFMTR_COLOR` and `RPLEXFMTR_RULES` environment variables.
 ///
 /// # Panics
 ///
@@ -17,51 +49,971 @@ use std::path::PathBuf;
 ///
 /// # Exits
 ///
-/// When [`Cli::parse()`] is called, [`valid_paths()`] will exit with a status code of `1` if an invalid path is given as an argument.
+/// When [`Rplexfmtr::parse()`] is called, [`valid_paths()`] will exit with a status code of `1` if an invalid path is given as an argument.
 ///
 /// # Example
 /// ```
-/// let args = Cli::parse();
-/// println!("{}", args.output_path);
+/// let args = Rplexfmtr::parse().args;
+/// println!("{:?}", args.output_path);
 /// ```
-#[derive(Parser)]
-#[command(author, version, about)]
+#[derive(clap::Args)]
 pub struct Cli {
     /// Input path(s) of video folder(s)
-    #[arg(short, long, value_parser = valid_paths, num_args = 1.. , required = true )]
+    #[arg(short, long, value_parser = valid_paths, num_args = 1.. , required_unless_present = "plan_in" )]
     pub input_paths: Vec<PathBuf>,
 
     /// Output Folder for Plex formatted media
-    #[arg(short,long,value_parser = valid_paths, num_args = 1) ]
-    pub output_path: PathBuf,
+    #[arg(short,long,value_parser = valid_paths, num_args = 1, env = "RPLEXFMTR_OUTPUT", global = true) ]
+    pub output_path: Option<PathBuf>,
+
+    /// How files are transferred to the output folder [default: move]
+    #[arg(short, long, value_enum, env = "RPLEXFMTR_TRANSFER", global = true)]
+    pub transfer: Option<TransferMode>,
+
+    /// True if the -t flag was given on the command line rather than through `RPLEXFMTR_TRANSFER` or the config file, filled in by [`crate::main()`]
+    #[arg(skip)]
+    pub transfer_given: bool,
+
+    /// Name of the library from the config file every input directory is renamed to, or one name per input directory
+    #[arg(short, long, value_name = "NAME", num_args = 1.., conflicts_with = "plan_in")]
+    pub library: Vec<String>,
+
+    /// The libraries the input directories can be renamed to, filled in by [`crate::config::load_config()`]
+    #[arg(skip)]
+    pub libraries: Vec<Library>,
+
+    /// The drop folders watched by the watch command, filled in by [`crate::config::load_config()`]
+    #[arg(skip)]
+    pub watch_folders: Vec<WatchFolder>,
+
+    /// Rules file matching file names to episodes without prompting [default: ~/.config/rplexfmtr/rules.toml]
+    #[arg(
+        long = "rules",
+        value_name = "FILE",
+        env = "RPLEXFMTR_RULES",
+        global = true
+    )]
+    pub rules_file: Option<PathBuf>,
+
+    /// The rules of the rules file, filled in by [`crate::rules::load_rules()`]
+    #[arg(skip)]
+    pub rules: Vec<Rule>,
+
+    /// Config file with the default values of the flags [default: ~/.config/rplexfmtr/config.toml]
+    #[arg(long, value_name = "FILE", env = "RPLEXFMTR_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// File name of the episodes, using the {series}, {season}, {episode} and {title} placeholders [default: "{series} S{season}E{episode}"]
+    #[arg(long, value_parser = parse_template, env = "RPLEXFMTR_TEMPLATE", global = true)]
+    pub template: Option<String>,
+
+    /// Minimum amount of digits of the season and episode numbers in the file names, padded with zeros [default: 0]
+    #[arg(long, value_name = "DIGITS", env = "RPLEXFMTR_PADDING", global = true)]
+    pub padding: Option<usize>,
+
+    /// Only list the files with these extensions, separated by commas [default: every file]
+    #[arg(
+        long,
+        value_name = "EXTENSION",
+        value_delimiter = ',',
+        env = "RPLEXFMTR_EXTENSIONS",
+        global = true
+    )]
+    pub extensions: Vec<String>,
+
+    /// When to use colors in the output [default: auto]
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        env = "RPLEXFMTR_COLOR",
+        global = true
+    )]
+    pub color: Option<ColorMode>,
+
+    /// Save the planned changes to a JSON or CSV file once the prompts are answered
+    #[arg(long, value_name = "PLAN")]
+    pub plan_out: Option<PathBuf>,
+
+    /// Execute the changes saved in a JSON or CSV plan file instead of prompting
+    #[arg(long, value_name = "PLAN", conflicts_with_all = ["input_paths", "tui", "picker", "titles"])]
+    pub plan_in: Option<PathBuf>,
+
+    /// Write the changes to a shell script, along with a script undoing them, instead of executing them
+    #[arg(long, value_name = "SCRIPT")]
+    pub emit_script: Option<PathBuf>,
+
+    /// Format of the preview of the changes
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+
+    /// Stream events while the files are transferred
+    #[arg(long, value_enum, value_name = "FORMAT", global = true)]
+    pub events: Option<EventFormat>,
+
+    /// Write the events to a file instead of standard output
+    #[arg(long, value_name = "FILE", requires = "events", global = true)]
+    pub events_file: Option<PathBuf>,
+
+    /// Keep transferring the remaining files when one fails and report the failures at the end
+    #[arg(long, global = true)]
+    pub keep_going: bool,
+
+    /// Put back every renamed file if any file fails to be renamed
+    #[arg(long, conflicts_with = "keep_going", global = true)]
+    pub atomic: bool,
+
+    /// Owner of the renamed files, as user, user:group or :group
+    #[arg(long, value_name = "USER[:GROUP]", value_parser = parse_owner, global = true)]
+    pub owner: Option<Owner>,
+
+    /// Permission bits of the renamed files, as an octal number such as 644
+    #[arg(long, value_parser = parse_mode, global = true)]
+    pub mode: Option<u32>,
+
+    /// Remove the input directories left empty or holding only junk files once every file is renamed
+    #[arg(long, global = true)]
+    pub cleanup: bool,
+
+    /// Patterns of the file names treated as junk by --cleanup, separated by commas, with a trailing / for folders [default: *.nfo,*.jpg,*.jpeg,*.png,*.sfv,*.srr,*.url,*-sample.*,*.sample.*,Sample/,Thumbs.db,.DS_Store]
+    #[arg(long, value_name = "PATTERN", value_delimiter = ',', global = true)]
+    pub junk: Vec<String>,
+
+    /// Order in which the selected files are numbered as episodes
+    #[arg(long, value_enum, default_value_t = SelectionOrder::Natural)]
+    pub order: SelectionOrder,
+
+    /// Choose files with a full screen picker instead of typing a range
+    #[arg(long)]
+    pub picker: bool,
+
+    /// Use a terminal user interface instead of prompts
+    #[arg(long, conflicts_with = "picker")]
+    pub tui: bool,
+
+    /// Ask for a CSV or TXT episode list giving the titles of the episodes of every input directory
+    #[arg(long)]
+    pub titles: bool,
+}
+
+impl Cli {
+    /// True if events are streamed to standard output, in which case the messages meant for the user are printed to standard error so they do not get mixed with the events.
+    pub fn events_on_stdout(&self) -> bool {
+        self.events.is_some() && self.events_file.is_none()
+    }
+
+    /// The [`TransferMode`] given with the -t flag, or [`TransferMode::Move`] if none was given.
+    pub fn transfer_mode(&self) -> TransferMode {
+        self.transfer.unwrap_or_default()
+    }
+
+    /// The template given with the --template flag, or [`DEFAULT_TEMPLATE`] if none was given.
+    pub fn template(&self) -> &str {
+        self.template.as_deref().unwrap_or(DEFAULT_TEMPLATE)
+    }
+
+    /// The padding given with the --padding flag, or `0` if none was given.
+    pub fn padding(&self) -> usize {
+        self.padding.unwrap_or_default()
+    }
 }
 
-/// Will move all files from a database to the output_path given by the command line.
+/// Template used for the file names of the episodes when none is given with the --template flag, as described in [`parse_template()`].
+pub const DEFAULT_TEMPLATE: &str = "{series} S{season}E{episode}";
+
+/// The command line argument parser, made of an optional [`Command`] and the [`Cli`] arguments used when no command is given.
 ///
-/// This function will first collect all of the episode entries from the database, create the folder structure for the particular show and season and move the episode to the new_path directory of the given episode. Will run a filesystem rename if the old_path and new_path directories are in the same file system, else will copy the file to new_path and delete file at old_path.
+/// Running rplexfmtr without a command is the same as running the `rename` command, so that the flags of [`Cli`] keep working on their own.
 ///
-/// # Panics
-/// - If the file in the old_path of the episode entry no longer exists the method will panic.
-/// - If the file in the old_path of the episode entry no longer has permissions to read the file, the method will panic.
-/// - If the new_path directory no longer has write permissions, this method will panic.
-pub async fn move_files(db: &sqlx::SqlitePool, args: &Cli) -> anyhow::Result<()> {
-    for show in select_all_shows(db).await? {
-        for episode in select_all_episodes(db, &show.series_name)
-            .await?
-            .into_iter()
+/// # Example
+/// ```
+/// let Rplexfmtr { command, args } = Rplexfmtr::parse();
+/// ```
+#[derive(Parser)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true
+)]
+pub struct Rplexfmtr {
+    /// Command to run, rename by default
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// The arguments of the rename flow when no command is given.
+    #[command(flatten)]
+    pub args: Cli,
+}
+
+/// Commands that can be run by rplexfmtr.
+///
+/// Used as the subcommand of [`Rplexfmtr`].
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Rename files, the same as giving the flags without a command
+    Rename(Box<Cli>),
+    /// Rename the files left over by the last run that was interrupted or failed
+    Resume,
+    /// Put back the files renamed by a past run, the last one by default
+    Undo {
+        /// Number of the run to undo, as shown by the history command
+        run: Option<i64>,
+    },
+    /// List the past runs, or the files renamed by one of them
+    History {
+        /// Number of the run to show the files of
+        run: Option<i64>,
+    },
+    /// Check that the files in the libraries follow the Plex naming and folder structure
+    Lint {
+        /// Library folders to check, every library of the config file by default
+        #[arg(value_parser = valid_paths)]
+        paths: Vec<PathBuf>,
+    },
+    /// List the files of input folders along with the series, season and episode guessed from their names
+    Scan {
+        /// Input folders to scan
+        #[arg(value_parser = valid_paths, required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Watch drop folders and rename the files saved to them once they are fully written
+    Watch {
+        /// Drop folders to watch, the ones of the config file by default
+        #[arg(value_parser = valid_paths)]
+        paths: Vec<PathBuf>,
+        /// Name of the library the files of the given folders are renamed to, the only library by default
+        #[arg(short, long, value_name = "NAME")]
+        library: Option<String>,
+        /// Series every file of the given folders belongs to, guessed from the file names by default
+        #[arg(short, long, value_name = "NAME")]
+        series: Option<String>,
+        /// Seconds the size of a file has to stay the same before it is renamed
+        #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+        settle: u64,
+    },
+    /// Import show metadata from TVmaze or TVDB JSON or CSV dumps, used for suggestions while prompting
+    ImportMetadata {
+        /// JSON or CSV dumps to import
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Name of the show the episodes belong to, for dumps that do not name it
+        #[arg(short, long, value_name = "NAME")]
+        show: Option<String>,
+    },
+}
+
+/// How [`move_files()`] transfers each file to its new path.
+///
+/// Used by the -t flag of [`Cli`] and saved along with the episodes in a plan file.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferMode {
+    /// Move the file, removing it from the input folder.
+    #[default]
+    Move,
+    /// Copy the file, leaving the original in the input folder.
+    Copy,
+    /// Create a hard link to the file. Both paths must be in the same file system.
+    Hardlink,
+    /// Create a symbolic link pointing to the original file.
+    Symlink,
+}
+
+/// Format used by [`preview_changes()`] to print the planned changes.
+///
+/// Used by the `--output` flag of [`Cli`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// A colored table meant to be read by the user.
+    Table,
+    /// A JSON array of the episodes meant to be read by other programs.
+    Json,
+}
+
+/// Order in which the files chosen by the user are assigned episode numbers.
+///
+/// Used by the `--order` flag of [`Cli`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum SelectionOrder {
+    /// Episodes are numbered following the natural order of the file names, no matter how the selection was typed.
+    Natural,
+    /// Episodes are numbered in the order the files were typed in the selection. eg.(3 1 2 numbers file 3 as episode 1)
+    Typed,
+}
+
+/// Will transfer all files from a database to their new_path using the [`TransferMode`] given by the command line.
+///
+/// This function will first collect all of the episode entries from the database, create the folder structure for the particular show and season and transfer the episode to the new_path of the given episode as follows:
+/// - [`TransferMode::Move`]: Will run a filesystem rename if the old_path and new_path directories are in the same file system, else will copy the file to new_path and delete file at old_path, as decided by [`strategy()`].
+/// - [`TransferMode::Copy`]: Will copy the file to new_path.
+/// - [`TransferMode::Hardlink`]: Will create a hard link to old_path at new_path.
+/// - [`TransferMode::Symlink`]: Will create a symbolic link at new_path pointing to the absolute path of old_path.
+///
+/// Episodes whose old_path is already their new_path are skipped.
+/// If the --events flag was given, an [`crate::events::Event`] is sent for every episode before any file is transferred, and then as each file is transferred, using [`Events`].\
+///
+/// If the --keep-going flag was given, an episode that fails to be transferred is recorded as a [`Failure`] and the remaining episodes are still transferred.
+/// Returns [`Outcome::Finished`] with every recorded failure, which is always empty without the --keep-going flag since the first error is returned right away.\
+///
+/// If the --atomic flag was given instead, the changes made before an episode fails are reverted with [`Journal::rollback()`] before returning the error, so that the files are either all renamed or left as they were.
+/// The progress and the history are updated with [`roll_back()`] even if some changes cannot be reverted, in which case the error lists them along with the error of the episode.\
+///
+/// The episodes left to transfer are kept in a [`Progress`] plan file, so that a run that fails or is interrupted can be completed with `rplexfmtr resume`. Unless this run is that resume, nothing is transferred while the plan file of the last run exists.
+/// A run rolled back by the --atomic flag has nothing left over, so its plan file is removed, unless it was resuming the last run, whose files are then left over again.
+/// Every transferred episode is also recorded in the persistent [`History`], so that the run can be put back later with `rplexfmtr undo`. A run that is rolled back is removed from the history.
+/// An episode is recorded as soon as its file is transferred, before the --owner and --mode flags are applied with [`set_owner_and_mode()`], so that a file whose owner or mode cannot be set is still rolled back, undone and left out of `rplexfmtr resume`.
+/// When SIGINT or SIGTERM is received, the current file is finished, or its partial copy is removed, and [`Outcome::Interrupted`] is returned along with the amount of files left over, once rolled back if the --atomic flag was given.
+///
+/// # Errors
+/// - If a file cannot be transferred, such as when its old_path no longer exists or cannot be read, or when the directory of its new_path cannot be written to, unless the --keep-going flag was given.
+/// - If the plan file of the last run exists and this run does not resume it, as checked with [`crate::plan::check_progress()`].
+/// - If the episodes cannot be read from the database, or if an event, the progress or the history cannot be written.
+pub async fn move_files(db: &sqlx::SqlitePool, args: &Cli) -> anyhow::Result<Outcome> {
+    handle_interrupts();
+    let mut events = Events::new(args)?;
+    let episodes = select_plan(db).await?;
+    let mut failures = Vec::new();
+    let mut journal = Journal::new(args.transfer_mode());
+    let resuming = progress_path().is_ok_and(|x| args.plan_in.as_ref() == Some(&x));
+    let mut progress = Progress::new(&episodes, args.transfer_mode(), resuming)?;
+    let mut history = History::new(args.transfer_mode()).await?;
+    for episode in &episodes {
+        events.send(EventKind::Planned, episode, None)?;
+    }
+    for episode in &episodes {
+        if interrupted() {
+            break;
+        }
+        let old_path = Path::new(&episode.old_path);
+        let new_path = Path::new(&episode.new_path);
+        if old_path == new_path {
+            events.send(EventKind::Skipped, episode, None)?;
+            progress.done(episode)?;
+            continue;
+        }
+        events.send(EventKind::Started, episode, None)?;
+        let result = match new_path.parent() {
+            Some(parent) => journal.create_dirs(parent),
+            None => Ok(()),
+        }
+        .and_then(|_| transfer_file(old_path, new_path, args.transfer_mode()));
+        let result = match result {
+            Ok(_) => {
+                journal.record(episode);
+                history.record(episode).await?;
+                progress.done(episode)?;
+                set_owner_and_mode(new_path, args)
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            if interrupted() {
+                break;
+            }
+            events.send(EventKind::Failed, episode, Some(e.to_string()))?;
+            if args.keep_going {
+                failures.push(Failure::new(episode, &e));
+                continue;
+            }
+            if args.atomic {
+                let message = match roll_back(journal, &mut events, &mut progress, history, &episodes)
+                    .await
+                {
+                    Ok(_) => format!(
+                        "{} could not be renamed, every file renamed in this run was put back",
+                        episode.old_path
+                    ),
+                    Err(rollback) => format!(
+                        "{} could not be renamed, and not every file renamed in this run could be put back: {rollback:#}",
+                        episode.old_path
+                    ),
+                };
+                return Err(e.context(message));
+            }
+            return Err(e);
+        }
+        let event = match args.transfer_mode() {
+            TransferMode::Move => EventKind::Moved,
+            TransferMode::Copy => EventKind::Copied,
+            TransferMode::Hardlink | TransferMode::Symlink => EventKind::Linked,
+        };
+        events.send(event, episode, None)?;
+    }
+    if interrupted() {
+        if args.atomic {
+            if let Err(e) = roll_back(journal, &mut events, &mut progress, history, &episodes).await
+            {
+                eprintln!("{}", format!("{e:#}").red());
+            }
+        }
+        return Ok(Outcome::Interrupted(progress.remaining()));
+    }
+    progress.finish()?;
+    Ok(Outcome::Finished(failures))
+}
+
+/// Reverts the changes made by an atomic run of [`move_files()`] with [`Journal::rollback()`], then resets the [`Progress`] with [`Progress::restore()`] and removes the run from the [`History`].
+///
+/// The progress and the history are updated even if some changes could not be reverted.
+///
+/// # Errors
+/// If any of the steps failed, listing the error of each of them.
+async fn roll_back(
+    journal: Journal,
+    events: &mut Events,
+    progress: &mut Progress,
+    history: History,
+    episodes: &[Episode],
+) -> anyhow::Result<()> {
+    let errors = [
+        journal.rollback(events).err(),
+        progress.restore(episodes).err(),
+        history.forget().await.err(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|x| format!("{x:#}"))
+    .collect::<Vec<_>>();
+    match errors.is_empty() {
+        true => Ok(()),
+        false => anyhow::bail!(errors.join("\n")),
+    }
+}
+
+/// Set once SIGINT or SIGTERM is received while [`move_files()`] is running.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Installs the handler for SIGINT and SIGTERM, or Ctrl+C on Windows, the first time it is called.
+///
+/// The first signal only sets [`interrupted()`], so that [`move_files()`] finishes renaming the current file, or aborts copying it with [`copy_file()`], before stopping.
+/// A second signal exits right away with a status code of `130`.
+pub fn handle_interrupts() {
+    static HANDLER: Once = Once::new();
+    HANDLER.call_once(|| {
+        let _ = ctrlc::set_handler(|| {
+            if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                exit(130);
+            }
+            eprintln!("Stopping after the current file, press Ctrl+C again to stop right away.");
+        });
+    });
+}
+
+/// True once SIGINT or SIGTERM has been received by the handler installed with [`handle_interrupts()`].
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Size of the chunks read and written by [`copy_file()`].
+const COPY_CHUNK_SIZE: usize = 1 << 20;
+
+/// Copies the file at old_path to new_path in chunks of [`COPY_CHUNK_SIZE`], preserving the metadata of the file with [`copy_metadata()`].
+///
+/// Stops between chunks once [`interrupted()`] is true. If the copy is interrupted or fails, the partial file at new_path is removed so that only the file at old_path is left.
+///
+/// # Errors
+/// If the file cannot be read or written, or with an [`std::io::ErrorKind::Interrupted`] error if the copy was interrupted.
+///
+/// # Example
+/// ```
+/// copy_file(Path::new("/home/user/show1.mkv"), Path::new("/mnt/media/Show/Season 1/Show S1E1.mkv"))?;
+/// ```
+pub fn copy_file(old_path: &Path, new_path: &Path) -> anyhow::Result<()> {
+    let mut reader = std::fs::File::open(old_path)?;
+    let metadata = reader.metadata()?;
+    let mut writer = std::fs::File::create(new_path)?;
+    let mut buffer = vec![0; COPY_CHUNK_SIZE];
+    let result = loop {
+        if interrupted() {
+            break Err(std::io::Error::from(std::io::ErrorKind::Interrupted));
+        }
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => break Err(e),
+        };
+        if let Err(e) = writer.write_all(&buffer[..read]) {
+            break Err(e);
+        }
+    }
+    .and_then(|_| copy_metadata(&metadata, old_path, &writer, new_path));
+    if let Err(e) = result {
+        drop(writer);
+        let _ = std::fs::remove_file(new_path);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Copies the metadata of the file at old_path, read before copying it, to the copy at new_path.
+///
+/// The access and modification times are kept so that Plex® Media Server orders the episodes by when they were added, along with the permission bits.
+/// On Unix, the extended attributes in the `user` namespace are copied as well with [`copy_xattrs()`], before the permission bits so that a read-only file still gets them.
+/// If the file systems do not support extended attributes or do not allow them to be copied, a warning is printed and the copy is kept.
+///
+/// # Errors
+/// If the times or permissions cannot be set, or if the extended attributes cannot be copied for another reason.
+pub fn copy_metadata(
+    metadata: &std::fs::Metadata,
+    old_path: &Path,
+    writer: &std::fs::File,
+    new_path: &Path,
+) -> std::io::Result<()> {
+    writer.set_times(
+        std::fs::FileTimes::new()
+            .set_accessed(metadata.accessed()?)
+            .set_modified(metadata.modified()?),
+    )?;
+    #[cfg(unix)]
+    match copy_xattrs(old_path, new_path) {
+        Err(e)
+            if e.raw_os_error().is_some_and(|x| {
+                [nix::errno::Errno::ENOTSUP, nix::errno::Errno::EPERM]
+                    .contains(&nix::errno::Errno::from_raw(x))
+            }) =>
         {
-            std::fs::create_dir_all(args.output_path.join(episode.clone().series_name).join(
-                "Season ".to_owned() + &episode.season.to_string(),
-            ))?;
-            match std::fs::rename(episode.clone().old_path, episode.clone().new_path) {
-                Ok(_) => {}
-                Err(_) => {
-                    std::fs::copy(episode.clone().old_path, episode.clone().new_path)?;
-                    std::fs::remove_file(episode.clone().old_path)?;
+            eprintln!(
+                "{}",
+                format!(
+                    "The extended attributes of {} were not copied: {e}",
+                    old_path.to_str().unwrap()
+                )
+                .yellow()
+            );
+        }
+        result => result?,
+    }
+    #[cfg(not(unix))]
+    let _ = (old_path, new_path);
+    writer.set_permissions(metadata.permissions())?;
+    Ok(())
+}
+
+/// Copies the extended attributes in the `user` namespace of the file at old_path to the file at new_path.
+///
+/// Nothing is copied if the file system of old_path does not support extended attributes, since the file cannot have any.
+///
+/// # Errors
+/// If the extended attributes cannot be listed, read or set.
+#[cfg(unix)]
+pub fn copy_xattrs(old_path: &Path, new_path: &Path) -> std::io::Result<()> {
+    let names = match xattr::list(old_path) {
+        Err(e) if e.raw_os_error() == Some(nix::errno::Errno::ENOTSUP as i32) => return Ok(()),
+        names => names?,
+    };
+    for name in names {
+        if !name.to_string_lossy().starts_with("user.") {
+            continue;
+        }
+        if let Some(value) = xattr::get(old_path, &name)? {
+            xattr::set(new_path, &name, &value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Sets the owner given with the --owner flag and the mode given with the --mode flag on a transferred file, if any of them were given.
+///
+/// Symbolic links keep their mode and only have their own owner changed, leaving the file they point to untouched.
+/// Since a hard link is the same file as the original, changing it changes the original as well.
+///
+/// # Errors
+/// If the owner or mode cannot be changed, which usually requires being the owner of the file or running as root.
+///
+/// # Example
+/// ```
+/// set_owner_and_mode(Path::new("/home/user/output/Show/Season 1/Show S1E1.mkv"), &args)?;
+/// ```
+#[cfg(unix)]
+pub fn set_owner_and_mode(path: &Path, args: &Cli) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let is_symlink = std::fs::symlink_metadata(path)?.file_type().is_symlink();
+    if let Some(owner) = args.owner {
+        let uid = owner.uid.map(nix::unistd::Uid::from_raw);
+        let gid = owner.gid.map(nix::unistd::Gid::from_raw);
+        let follow = match is_symlink {
+            true => nix::fcntl::AtFlags::AT_SYMLINK_NOFOLLOW,
+            false => nix::fcntl::AtFlags::empty(),
+        };
+        nix::unistd::fchownat(nix::fcntl::AT_FDCWD, path, uid, gid, follow)?;
+    }
+    if let (Some(mode), false) = (args.mode, is_symlink) {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+/// Sets the owner and mode of a transferred file.
+///
+/// Does nothing on this platform, since --owner cannot be parsed and --mode does not apply.
+#[cfg(not(unix))]
+pub fn set_owner_and_mode(_path: &Path, _args: &Cli) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// How a run of [`move_files()`] ended.
+#[derive(Clone, Debug)]
+pub enum Outcome {
+    /// Every episode was transferred, except for the [`Failure`]s recorded when the --keep-going flag was given.
+    Finished(Vec<Failure>),
+    /// SIGINT or SIGTERM was received before every episode was transferred, holding the amount of episodes left over.
+    Interrupted(usize),
+}
+
+/// Exit code of rplexfmtr when the --keep-going flag was given and some of the files could not be renamed, so that it can be told apart from the exit code `1` of any other error.
+pub const PARTIAL_FAILURE_EXIT_CODE: i32 = 2;
+
+/// An episode that could not be transferred by [`move_files()`] when the --keep-going flag was given.
+///
+/// Derives [`tabled::Tabled`] so that the failures can be shown with [`failure_report()`].
+#[derive(Clone, Debug, tabled::Tabled)]
+pub struct Failure {
+    /// Holds the series name of the episode.
+    pub series_name: String,
+    /// Holds the season number of the episode.
+    pub season: u32,
+    /// Holds the episode number of the episode.
+    pub episode: u32,
+    /// Holds the current path of the file of the episode.
+    pub old_path: String,
+    /// Holds the output path of the file of the episode.
+    pub new_path: String,
+    /// The [`std::io::ErrorKind`] of the error, or `Other` if the error did not come from the file system.
+    pub kind: String,
+    /// The message of the error.
+    pub error: String,
+}
+
+impl Failure {
+    /// Records the failure of the given episode with the error returned while transferring it.
+    ///
+    /// # Example
+    /// ```
+    /// let failure = Failure::new(&episode, &anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::NotFound)));
+    /// assert_eq!(failure.kind, "NotFound");
+    /// ```
+    pub fn new(episode: &Episode, error: &anyhow::Error) -> Failure {
+        let kind = match error.downcast_ref::<std::io::Error>() {
+            Some(e) => format!("{:?}", e.kind()),
+            None => format!("{:?}", std::io::ErrorKind::Other),
+        };
+        Failure {
+            series_name: episode.series_name.clone(),
+            season: episode.season,
+            episode: episode.episode,
+            old_path: episode.old_path.clone(),
+            new_path: episode.new_path.clone(),
+            kind,
+            error: error.to_string(),
+        }
+    }
+}
+
+/// Returns the failures recorded by [`move_files()`] as a table of a rounded style with a red border, along with a line counting them.
+///
+/// # Example
+/// ```
+/// let failures = move_files(&db, &args).await?;
+/// if !failures.is_empty() {
+///     eprintln!("{}", failure_report(&failures, amount_episodes));
+/// }
+/// ```
+pub fn failure_report(failures: &[Failure], amount_episodes: usize) -> String {
+    format!(
+        "{} of {} files could not be renamed:\n{}",
+        failures.len().to_string().red(),
+        amount_episodes,
+        tabled::Table::new(failures)
+            .with(tabled::settings::Style::rounded())
+            .with(border_color(tabled::settings::Color::FG_RED))
+    )
+}
+
+/// Returns the border of a table drawn with the given color, or without any color if colors are disabled by the --color flag or the environment.
+pub fn border_color(color: tabled::settings::Color) -> tabled::settings::style::BorderColor {
+    match colored::control::SHOULD_COLORIZE.should_colorize() {
+        true => tabled::settings::style::BorderColor::default()
+            .top(color.clone())
+            .bottom(color.clone())
+            .left(color.clone())
+            .right(color.clone())
+            .corner_top_left(color.clone())
+            .corner_top_right(color.clone())
+            .corner_bottom_left(color.clone())
+            .corner_bottom_right(color),
+        false => tabled::settings::style::BorderColor::default(),
+    }
+}
+
+/// How a single file is transferred to its new path, decided by [`strategy()`] before the file is touched.
+///
+/// Shown in the preview by [`preview_changes()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// Rename the file, since old_path and new_path are in the same file system.
+    Rename,
+    /// Copy the file and then delete it from old_path, since new_path is in another file system.
+    CopyDelete,
+    /// Copy the file, leaving it at old_path.
+    Copy,
+    /// Create a hard link to the file.
+    Hardlink,
+    /// Create a symbolic link pointing to the file.
+    Symlink,
+}
+
+impl std::fmt::Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Strategy::Rename => "rename",
+            Strategy::CopyDelete => "copy + delete",
+            Strategy::Copy => "copy",
+            Strategy::Hardlink => "hard link",
+            Strategy::Symlink => "symbolic link",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Decides how the file at old_path is transferred to new_path with the given [`TransferMode`].
+///
+/// With [`TransferMode::Move`], the device IDs of old_path and of the directory new_path will be created in are compared, as returned by [`crate::preflight::device()`].
+/// The file is renamed if they are the same, or if either cannot be read, and copied and deleted if they differ. The other modes always use their own strategy.
+///
+/// # Example
+/// ```
+/// let strategy = strategy(Path::new("/home/user/show1.mkv"), Path::new("/mnt/media/Show/Season 1/Show S1E1.mkv"), TransferMode::Move);
+/// assert_eq!(strategy, Strategy::CopyDelete);
+/// ```
+pub fn strategy(old_path: &Path, new_path: &Path, mode: TransferMode) -> Strategy {
+    match mode {
+        TransferMode::Move => {
+            let target_dir = new_path
+                .parent()
+                .and_then(crate::preflight::existing_ancestor);
+            match (
+                crate::preflight::device(old_path),
+                target_dir.and_then(crate::preflight::device),
+            ) {
+                (Some(old_device), Some(new_device)) if old_device != new_device => {
+                    Strategy::CopyDelete
                 }
+                _ => Strategy::Rename,
             }
         }
+        TransferMode::Copy => Strategy::Copy,
+        TransferMode::Hardlink => Strategy::Hardlink,
+        TransferMode::Symlink => Strategy::Symlink,
+    }
+}
+
+/// Transfers a single file from its old path to its new path using the given [`TransferMode`], as described in [`move_files()`].
+///
+/// The file is transferred with the [`Strategy`] returned by [`strategy()`].
+/// If a rename fails with [`std::io::ErrorKind::CrossesDevices`] (EXDEV), which can happen when the device IDs do not tell file systems apart, such as with bind mounts, the file is copied and deleted instead.
+/// Any other error, such as a missing file or a permission problem, is returned as is.
+///
+/// # Example
+/// ```
+/// transfer_file(Path::new("/home/user/show1.mkv"), Path::new("/home/user/output/Show/Season 1/Show S1E1.mkv"), TransferMode::Copy)?;
+/// ```
+pub fn transfer_file(old_path: &Path, new_path: &Path, mode: TransferMode) -> anyhow::Result<()> {
+    match strategy(old_path, new_path, mode) {
+        Strategy::Rename => match std::fs::rename(old_path, new_path) {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                copy_file(old_path, new_path)?;
+                std::fs::remove_file(old_path)?;
+            }
+            Err(e) => return Err(e.into()),
+        },
+        Strategy::CopyDelete => {
+            copy_file(old_path, new_path)?;
+            std::fs::remove_file(old_path)?;
+        }
+        Strategy::Copy => copy_file(old_path, new_path)?,
+        Strategy::Hardlink => std::fs::hard_link(old_path, new_path)?,
+        Strategy::Symlink => {
+            let target = std::fs::canonicalize(old_path)?;
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(target, new_path)?;
+            #[cfg(windows)]
+            std::os::windows::fs::symlink_file(target, new_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// A row of the preview shown by [`preview_changes()`], made of an [`Episode`], the name of its [`Library`] and the [`Strategy`] used to transfer its file.
+#[derive(Clone, Debug, Tabled, serde::Serialize)]
+pub struct PreviewRow {
+    /// The episode of the row.
+    #[tabled(inline)]
+    #[serde(flatten)]
+    pub episode: Episode,
+    /// The name of the library the episode is renamed to, empty for the output path given with the -o flag.
+    pub library: String,
+    /// How the file of the episode will be transferred.
+    pub strategy: Strategy,
+}
+
+/// Matches the `{title}` placeholder of a template along with the spaces, dashes, dots and underscores before it, which [`episode_path()`] removes when an episode has no title.
+static TITLE_PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[\s\-_.]*\{title\}").unwrap());
+
+/// Given the library, series name, season number, episode number, episode title and the current path of the episode, will return the path the episode will be moved to.
+///
+/// The path follows the Plex® Media Server folder structure for TV Shows, `library_path/series_name/Season season/file_name.extension`, where the extension is taken from the given file.
+/// The file name is the template of the [`Library`], `series_name SseasonEepisode` by default, with the season and episode numbers padded with zeros to the amount of digits of its padding.
+/// The title replaces the `{title}` placeholder of the template, or is added after ` - ` if the template has none, as in `Show - S01E01 - Pilot`. Without a title, the placeholder is removed along with the spaces, dashes, dots and underscores before it.
+///
+/// # Panics
+/// If the file has no file name or it cannot be unwrapped to a [`&str`].
+///
+/// # Example
+/// ```
+/// let new_path = episode_path(&args.libraries[0], "Show", 1, 2, Some("Pilot"), Path::new("/home/user/show2.mkv"));
+/// assert_eq!(new_path, PathBuf::from("/home/user/output/Show/Season 1/Show S1E2 - Pilot.mkv"));
+/// ```
+pub fn episode_path(
+    library: &Library,
+    series_name: &str,
+    season: u32,
+    episode: u32,
+    title: Option<&str>,
+    file: &Path,
+) -> PathBuf {
+    let padding = library.padding;
+    let file_name = library
+        .template
+        .replace("{series}", series_name)
+        .replace("{season}", &format!("{season:0padding$}"))
+        .replace("{episode}", &format!("{episode:0padding$}"));
+    let file_name = match (title, file_name.contains("{title}")) {
+        (Some(title), true) => file_name.replace("{title}", title),
+        (Some(title), false) => format!("{file_name} - {title}"),
+        (None, _) => TITLE_PLACEHOLDER
+            .replace_all(&file_name, "")
+            .trim_start_matches([' ', '-', '_', '.'])
+            .to_owned(),
+    };
+    library
+        .path
+        .join(series_name)
+        .join("Season ".to_owned() + &season.to_string())
+        .join(
+            file_name
+                + "."
+                + file
+                    .file_name()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .split('.')
+                    .collect::<Vec<_>>()
+                    .last()
+                    .unwrap(),
+        )
+}
+
+/// Applies an [`Edit`] typed at the edit step to the episode entry shown in the given row of the preview table.
+///
+/// The rows are numbered in the order returned by [`select_plan()`], which is the order shown by [`preview_changes()`]. Depending on the edit:
+/// - [`Edit::Episode`] and [`Edit::Season`]: The episode number or season is changed and the output path is rebuilt with [`episode_path()`] in the same [`Library`], found with [`library_of()`], replacing any file name given with [`Edit::Name`].
+/// - [`Edit::Exclude`]: The entry is removed with [`delete_episode()`], so its file is not renamed.
+/// - [`Edit::Name`]: The file name of the output path is replaced, keeping the file in the same season folder. The extension of the file is added if the name does not already end with it.
+///
+/// # Errors
+/// If the row is not in the preview table or if the new output path is already used by another row. The database is left unchanged.
+///
+/// # Example
+/// ```
+/// apply_edit(&db, &args, Edit::Season(3, 2)).await?;
+/// ```
+pub async fn apply_edit(db: &sqlx::SqlitePool, args: &Cli, edit: Edit) -> anyhow::Result<()> {
+    let plan = select_plan(db).await?;
+    let row = match &edit {
+        Edit::Episode(row, _) | Edit::Season(row, _) | Edit::Exclude(row) | Edit::Name(row, _) => {
+            *row
+        }
+    };
+    let Some(episode) = plan.get(row) else {
+        anyhow::bail!("There is no row {row} in the preview.");
+    };
+    let old_path = Path::new(&episode.old_path);
+    let library = library_of(&args.libraries, Path::new(&episode.new_path));
+    let (season, number, new_path) = match (edit, library) {
+        (Edit::Exclude(_), _) => {
+            delete_episode(db, &episode.old_path).await?;
+            return Ok(());
+        }
+        (Edit::Episode(..) | Edit::Season(..), None) => {
+            anyhow::bail!("Row {row} is not in any library.")
+        }
+        (Edit::Episode(_, number), Some(library)) => (
+            episode.season,
+            number,
+            episode_path(
+                library,
+                &episode.series_name,
+                episode.season,
+                number,
+                episode.title.as_deref(),
+                old_path,
+            ),
+        ),
+        (Edit::Season(_, season), Some(library)) => (
+            season,
+            episode.episode,
+            episode_path(
+                library,
+                &episode.series_name,
+                season,
+                episode.episode,
+                episode.title.as_deref(),
+                old_path,
+            ),
+        ),
+        (Edit::Name(_, name), _) => {
+            let new_path = Path::new(&episode.new_path);
+            let file_name = match new_path.extension().and_then(|x| x.to_str()) {
+                Some(extension) if !name.ends_with(&(".".to_owned() + extension)) => {
+                    name + "." + extension
+                }
+                _ => name,
+            };
+            (
+                episode.season,
+                episode.episode,
+                new_path.with_file_name(file_name),
+            )
+        }
+    };
+    if let Some(other) = plan
+        .iter()
+        .position(|x| Path::new(&x.new_path) == new_path && x.old_path != episode.old_path)
+    {
+        anyhow::bail!(
+            "Row {other} is already renamed to {}.",
+            new_path.to_str().unwrap()
+        );
     }
+    update_episode(db, &episode.old_path, season, number, new_path).await?;
     Ok(())
 }
 
@@ -71,15 +1023,16 @@ pub async fn move_files(db: &sqlx::SqlitePool, args: &Cli) -> anyhow::Result<()>
 /// Then flattens the Ok entries into a [`Vec<std::fs::DirEntry>`], but this vector may contain folders, which isn't valid.
 /// So this vector is filtered again to only contain [`std::fs::DirEntry`] entries that are files and the resulting iterator is collected again to a [`Vec<std::fs::DirEntry>`].
 /// Finally the vector containing the valid files is sorted by the file name in a natural order.
+/// If any extensions are given, such as the ones of the --extensions flag, only the files with one of them are kept, without regard to case.
 ///
 /// # Panics
 /// If the given path does not have read permissions.
 ///
 /// # Example
 /// ```
-/// let files = get_files("/home/user");
+/// let files = get_files("/home/user", &args.extensions);
 /// ```
-pub fn get_files(path: PathBuf) -> anyhow::Result<Vec<DirEntry>> {
+pub fn get_files(path: PathBuf, extensions: &[String]) -> anyhow::Result<Vec<DirEntry>> {
     let mut files = read_dir(path)?
         .collect::<Vec<_>>()
         .into_iter()
@@ -88,6 +1041,14 @@ pub fn get_files(path: PathBuf) -> anyhow::Result<Vec<DirEntry>> {
         .collect::<Vec<_>>()
         .into_iter()
         .filter(|x| x.file_type().unwrap().is_file())
+        .filter(|x| {
+            extensions.is_empty()
+                || Path::new(&x.file_name()).extension().is_some_and(|e| {
+                    extensions
+                        .iter()
+                        .any(|x| e.eq_ignore_ascii_case(x.trim_start_matches('.')))
+                })
+        })
         .collect::<Vec<_>>();
     files.sort_by(|a, b| {
         natord::compare(
@@ -98,27 +1059,71 @@ pub fn get_files(path: PathBuf) -> anyhow::Result<Vec<DirEntry>> {
     Ok(files)
 }
 
-/// Will print out the files in a directory given a valid directory.
+/// Given the output path, will return the names of the series folders already present in it, naturally sorted.
+///
+/// These are the series that have been renamed to the output path before, and are offered as completions when prompting for a series name.
+/// If the output path cannot be read the return vector will be empty.
+///
+/// # Panics
+/// If a folder name cannot be unwrapped to a [`&str`].
+///
+/// # Example
+/// ```
+/// let shows = get_show_names(&args.output_path)?;
+/// ```
+pub fn get_show_names(output_path: &Path) -> anyhow::Result<Vec<String>> {
+    let mut names = match read_dir(output_path) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|x| x.file_type().map(|x| x.is_dir()).unwrap_or(false))
+            .map(|x| x.file_name().to_str().unwrap().to_owned())
+            .filter(|x| valid_name(x))
+            .collect::<Vec<_>>(),
+        Err(_) => Vec::new(),
+    };
+    names.sort_by(|a, b| natord::compare(&a.to_ascii_lowercase(), &b.to_ascii_lowercase()));
+    Ok(names)
+}
+
+/// Will print out the given files of a directory.
 ///
-/// This function gets a vector of file names [`Vec<String>`], from  [`get_file_names()`] which itself gets the files from [`get_files()`] and then will print the entry numbers and file names to standard output.
+/// This function gets a vector of file names [`Vec<String>`], from  [`get_file_names()`] given the files of a directory, such as the ones returned by [`get_files()`] or left unmatched by [`crate::rules::match_directory()`], and then will print the entry numbers and file names to standard output.
 ///
 /// # Panics
-/// - path argument is invalid.
-/// - path does not have read permissions.
 /// - The file names cannot be unwraped to a [`&str`].
 ///
 /// # Example
 /// ```
-/// print_directory("/home/user/");
+/// print_directory(&get_files("/home/user/", &args.extensions)?);
 /// ```
-pub fn print_directory(path: PathBuf) -> anyhow::Result<()> {
-    let files = get_file_names(&get_files(path).unwrap())?;
+pub fn print_directory(files: &[DirEntry]) -> anyhow::Result<()> {
+    let files = get_file_names(files)?;
     for (num, file) in files.into_iter().enumerate() {
         println!("{num}. {file}");
     }
     Ok(())
 }
 
+/// Will print out the parts of a user's selection that were ignored by [`parse_range()`] and why.
+///
+/// Prints how many of the files in the directory were selected, followed by one line for every [`IgnoredToken`] with the token highlighted in red and the [`IgnoreReason`] next to it.
+///
+/// # Example
+/// ```
+/// let selection = parse_range(4, "0-2 4".to_owned())?;
+/// print_ignored(&selection, 4);
+/// ```
+pub fn print_ignored(selection: &Selection, amount_files: usize) {
+    println!(
+        "{} of {} files selected. The following were ignored:",
+        selection.indexes.len().to_string().yellow(),
+        amount_files.to_string().yellow()
+    );
+    for ignored in &selection.ignored {
+        println!("  {}: {}", ignored.token.red(), ignored.reason);
+    }
+}
+
 /// Given a reference to a [`Vec<std::fs::DirEntry>`] that only contains files, will then return a [`Result<Vec<String>>`].
 ///
 /// This function first converts the given vector to an iterator to which  maps the DirEntry to a file name that is a owned string and then collects it into a Vector of [`String`] if the file name can be unwraped to an [`&str`].
@@ -135,62 +1140,120 @@ pub fn get_file_names(files: &[DirEntry]) -> anyhow::Result<Vec<String>> {
 
 /// Prints the renaming changes before and after in a table to standard output given a database with episode entries.
 ///
-/// The table is of a rounded style with a green border, with individually colored columns as follows:
+/// The table is of a rounded style with a green border, with the row number in the first column, followed by individually colored columns as follows:
 /// - series_name = Red.
 /// - season = Yellow.
 /// - episode = Cyan.
+/// - title = White, empty when the episode has no title.
 /// - old_path = Blue.
 /// - new_path = Green.
+/// - library = Blue, the name of the [`Library`] the episode is renamed to.
+/// - strategy = Magenta, the [`Strategy`] used to transfer the file.
 ///
-/// First the episodes are all retrieved from the database and pushes the episode entries to a vector.
-/// Then the vector is turned into table using [`tabled::Table::new()`] function with the style mentioned above.
-pub async fn preview_changes(db: &sqlx::SqlitePool) -> anyhow::Result<()> {
-    clearscreen::clear()?;
-    let mut entries: Vec<Episode> = Vec::new();
-    for show in select_all_shows(db).await? {
-        for episode in select_all_episodes(db, &show.series_name).await? {
-            entries.push(episode);
+/// First the episodes are all retrieved from the database using [`select_plan()`].
+/// Then the vector is turned into table using [`tabled::Table::new()`] function with the style mentioned above.\
+///
+/// Below the table, the amount of files renamed to each library is shown when there are named libraries. Then the amount of data that will be copied, whether with [`TransferMode::Copy`] or to another file system, and any problem that would make [`move_files()`] fail are shown, as found by [`crate::preflight::preflight()`].
+/// If the --cleanup flag was given, the input directories that will be removed are shown as well, as returned by [`crate::cleanup::plan_cleanup()`].\
+///
+/// If the [`OutputFormat`] given by the --output flag is [`OutputFormat::Json`], the screen is not cleared and the episodes are printed as a JSON array instead, in the same order as the rows of the table. The problems are then printed to standard error.
+pub async fn preview_changes(db: &sqlx::SqlitePool, args: &Cli) -> anyhow::Result<()> {
+    let episodes = select_plan(db).await?;
+    let mut report = preflight(&episodes, args.transfer_mode()).report();
+    if args.cleanup {
+        report += &cleanup_report(&plan_cleanup(
+            &cleanup_dirs(args, &episodes),
+            &episodes,
+            args,
+        ));
+    }
+    let mut counts: Vec<(&Library, usize)> = Vec::new();
+    for episode in &episodes {
+        if let Some(library) = library_of(&args.libraries, Path::new(&episode.new_path)) {
+            match counts.iter_mut().find(|(x, _)| *x == library) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((library, 1)),
+            }
         }
     }
+    if counts.len() > 1 || counts.iter().any(|(x, _)| x.name.is_some()) {
+        report = counts
+            .iter()
+            .map(|(library, count)| {
+                format!(
+                    "{} files will be renamed to {}.\n",
+                    count.to_string().yellow(),
+                    library.to_string().blue()
+                )
+            })
+            .collect::<String>()
+            + &report;
+    }
+    let entries = episodes
+        .into_iter()
+        .map(|episode| PreviewRow {
+            library: library_of(&args.libraries, Path::new(&episode.new_path))
+                .and_then(|x| x.name.clone())
+                .unwrap_or_default(),
+            strategy: strategy(
+                Path::new(&episode.old_path),
+                Path::new(&episode.new_path),
+                args.transfer_mode(),
+            ),
+            episode,
+        })
+        .collect::<Vec<_>>();
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        eprint!("{report}");
+        return Ok(());
+    }
+    clearscreen::clear()?;
     println!(
         "{}",
-        tabled::Table::new(entries)
+        tabled::Table::builder(entries)
+            .index()
+            .build()
             .with(tabled::settings::Style::rounded())
+            .with(border_color(tabled::settings::Color::FG_GREEN))
             .with(
-                tabled::settings::style::BorderColor::default()
-                    .top(tabled::settings::Color::FG_GREEN)
-                    .bottom(tabled::settings::Color::FG_GREEN)
-                    .left(tabled::settings::Color::FG_GREEN)
-                    .right(tabled::settings::Color::FG_GREEN)
-                    .corner_top_left(tabled::settings::Color::FG_GREEN)
-                    .corner_top_right(tabled::settings::Color::FG_GREEN)
-                    .corner_bottom_left(tabled::settings::Color::FG_GREEN)
-                    .corner_bottom_right(tabled::settings::Color::FG_GREEN)
-            )
-            .with(
-                tabled::settings::Modify::new(tabled::settings::object::Columns::single(0)).with(
+                tabled::settings::Modify::new(tabled::settings::object::Columns::single(1)).with(
                     tabled::settings::Format::content(|s| s.bright_red().to_string())
                 )
             )
             .with(
-                tabled::settings::Modify::new(tabled::settings::object::Columns::single(1)).with(
+                tabled::settings::Modify::new(tabled::settings::object::Columns::single(2)).with(
                     tabled::settings::Format::content(|s| s.yellow().to_string())
                 )
             )
             .with(
-                tabled::settings::Modify::new(tabled::settings::object::Columns::single(2))
+                tabled::settings::Modify::new(tabled::settings::object::Columns::single(3))
                     .with(tabled::settings::Format::content(|s| s.cyan().to_string()))
             )
             .with(
-                tabled::settings::Modify::new(tabled::settings::object::Columns::single(3)).with(
+                tabled::settings::Modify::new(tabled::settings::object::Columns::single(4))
+                    .with(tabled::settings::Format::content(|s| s.white().to_string()))
+            )
+            .with(
+                tabled::settings::Modify::new(tabled::settings::object::Columns::single(5)).with(
                     tabled::settings::Format::content(|s| s.bright_blue().to_string())
                 )
             )
             .with(
-                tabled::settings::Modify::new(tabled::settings::object::Columns::single(4)).with(
+                tabled::settings::Modify::new(tabled::settings::object::Columns::single(6)).with(
                     tabled::settings::Format::content(|s| s.bright_green().to_string())
                 )
             )
+            .with(
+                tabled::settings::Modify::new(tabled::settings::object::Columns::single(7))
+                    .with(tabled::settings::Format::content(|s| s.blue().to_string()))
+            )
+            .with(
+                tabled::settings::Modify::new(tabled::settings::object::Columns::single(8)).with(
+                    tabled::settings::Format::content(|s| s.magenta().to_string())
+                )
+            )
     );
+    print!("{report}");
     Ok(())
 }
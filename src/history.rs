@@ -0,0 +1,302 @@
+use crate::database::Episode;
+use crate::files::{border_color, transfer_file, Failure, TransferMode};
+use colored::*;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use sqlx::FromRow;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tabled::Tabled;
+
+/// Returns the path of the database holding the history of the runs, `rplexfmtr/history.db` inside of the data directory of the user.
+///
+/// # Errors
+/// If the data directory of the user cannot be found.
+pub fn history_path() -> anyhow::Result<PathBuf> {
+    match dirs::data_dir() {
+        Some(dir) => Ok(dir.join("rplexfmtr").join("history.db")),
+        None => anyhow::bail!("Could not find the data directory to keep the history of the runs."),
+    }
+}
+
+/// Opens the database at [`history_path()`], creating it along with its tables if it does not exist yet.
+///
+/// Unlike the transient database of [`crate::database`], this database is kept on disk so that past runs can be listed with `rplexfmtr history` and put back with `rplexfmtr undo`.
+///
+/// # Errors
+/// If the database cannot be created or opened.
+///
+/// # Example
+/// ```
+/// let history = open_history().await?;
+/// ```
+pub async fn open_history() -> anyhow::Result<SqlitePool> {
+    let path = history_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let db = SqlitePool::connect_with(
+        SqliteConnectOptions::from_str("sqlite:")?
+            .filename(path)
+            .create_if_missing(true),
+    )
+    .await?;
+    sqlx::query("CREATE TABLE IF NOT EXISTS runs (id INTEGER PRIMARY KEY AUTOINCREMENT, started TEXT NOT NULL DEFAULT (datetime('now', 'localtime')), transfer_mode TEXT NOT NULL, undone INTEGER NOT NULL DEFAULT 0);")
+        .execute(&db)
+        .await?;
+    sqlx::query("CREATE TABLE IF NOT EXISTS run_episodes (run_id INTEGER NOT NULL REFERENCES runs(id), position INTEGER NOT NULL, series_name TEXT, season INTEGER NOT NULL, episode INTEGER NOT NULL, old_path TEXT NOT NULL, new_path TEXT NOT NULL, undone INTEGER NOT NULL DEFAULT 0);")
+        .execute(&db)
+        .await?;
+    Ok(db)
+}
+
+/// A past run of [`crate::files::move_files()`] as listed by `rplexfmtr history`.
+#[derive(Clone, Debug, FromRow, Tabled)]
+pub struct Run {
+    /// Number of the run, given to `rplexfmtr undo` and `rplexfmtr history`.
+    pub id: i64,
+    /// Local date and time the run started at.
+    pub started: String,
+    /// The [`TransferMode`] of the run, in lowercase.
+    pub transfer_mode: String,
+    /// Amount of files renamed by the run.
+    pub files: i64,
+    /// Whether the run was put back with `rplexfmtr undo`.
+    pub undone: bool,
+}
+
+/// Records the episodes transferred by a run of [`crate::files::move_files()`] in the database at [`history_path()`].
+///
+/// The run is only added to the history once its first episode is recorded, so runs that did not rename anything are left out.
+/// If the data directory of the user cannot be found, a warning is printed and nothing is recorded.
+///
+/// # Example
+/// ```
+/// let mut history = History::new(TransferMode::Move).await?;
+/// transfer_file(old_path, new_path, TransferMode::Move)?;
+/// history.record(&episode).await?;
+/// ```
+pub struct History {
+    db: Option<SqlitePool>,
+    transfer_mode: TransferMode,
+    run: Option<i64>,
+    recorded: i64,
+}
+
+impl History {
+    /// Opens the history for a run transferring files with the given [`TransferMode`].
+    ///
+    /// # Errors
+    /// If the database cannot be opened with [`open_history()`].
+    pub async fn new(transfer_mode: TransferMode) -> anyhow::Result<History> {
+        let db = match history_path() {
+            Ok(_) => Some(open_history().await?),
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    format!("{e} The run cannot be undone with `rplexfmtr undo`.").yellow()
+                );
+                None
+            }
+        };
+        Ok(History {
+            db,
+            transfer_mode,
+            run: None,
+            recorded: 0,
+        })
+    }
+
+    /// Records that the file of the given episode was transferred to its new_path.
+    ///
+    /// The paths are made absolute with [`Episode::absolute()`], so that `rplexfmtr undo` puts back the same files no matter which directory it is run from.
+    ///
+    /// # Errors
+    /// If the current directory cannot be found, or if the episode cannot be written to the database.
+    pub async fn record(&mut self, episode: &Episode) -> anyhow::Result<()> {
+        let Some(db) = &self.db else {
+            return Ok(());
+        };
+        let episode = episode.absolute()?;
+        let run = match self.run {
+            Some(run) => run,
+            None => {
+                let run = sqlx::query("INSERT INTO runs (transfer_mode) VALUES (?);")
+                    .bind(serde_json::to_value(self.transfer_mode)?.as_str())
+                    .execute(db)
+                    .await?
+                    .last_insert_rowid();
+                self.run = Some(run);
+                run
+            }
+        };
+        sqlx::query("INSERT INTO run_episodes (run_id, position, series_name, season, episode, old_path, new_path) VALUES (?,?,?,?,?,?,?);")
+            .bind(run)
+            .bind(self.recorded)
+            .bind(&episode.series_name)
+            .bind(episode.season)
+            .bind(episode.episode)
+            .bind(&episode.old_path)
+            .bind(&episode.new_path)
+            .execute(db)
+            .await?;
+        self.recorded += 1;
+        Ok(())
+    }
+
+    /// Removes the run from the history, which is needed when every change it made is rolled back.
+    ///
+    /// # Errors
+    /// If the run cannot be removed from the database.
+    pub async fn forget(self) -> anyhow::Result<()> {
+        if let (Some(db), Some(run)) = (&self.db, self.run) {
+            delete_run(db, run).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Removes the given run and its episodes from the history.
+async fn delete_run(db: &SqlitePool, run: i64) -> anyhow::Result<()> {
+    sqlx::query("DELETE FROM run_episodes WHERE run_id = ?;")
+        .bind(run)
+        .execute(db)
+        .await?;
+    sqlx::query("DELETE FROM runs WHERE id = ?;")
+        .bind(run)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Returns every run in the history, from the oldest to the most recent.
+///
+/// # Example
+/// ```
+/// let runs = select_runs(&open_history().await?).await?;
+/// ```
+pub async fn select_runs(db: &SqlitePool) -> anyhow::Result<Vec<Run>> {
+    Ok(sqlx::query_as::<_, Run>(
+        "SELECT id, started, transfer_mode, (SELECT COUNT(*) FROM run_episodes WHERE run_id = id) AS files, undone FROM runs ORDER BY id;",
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+/// Returns the episodes renamed by the given run, in the order they were renamed.
+///
+/// # Errors
+/// If there is no run with the given number in the history.
+pub async fn select_run_episodes(db: &SqlitePool, run: i64) -> anyhow::Result<Vec<Episode>> {
+    if !select_runs(db).await?.iter().any(|x| x.id == run) {
+        anyhow::bail!("There is no run {run} in the history.");
+    }
+    Ok(sqlx::query_as::<_, Episode>(
+        "SELECT series_name, season, episode, old_path, new_path FROM run_episodes WHERE run_id = ? ORDER BY position;",
+    )
+    .bind(run)
+    .fetch_all(db)
+    .await?)
+}
+
+/// Puts back the files renamed by the given run, or by the most recent run that was not undone yet if none is given, returning the number of the run and the amount of files put back.
+///
+/// The episodes are reverted from the most recent to the oldest. Moved files are moved back to their old_path, recreating its folder if needed, while copies and links are removed from their new_path.
+/// The season and series folders left empty are then removed.
+/// Reverting does not stop at the first error, so that as much as possible is put back. The episodes that were reverted are marked as undone, and so is the run once all of them are, so that undoing it again only retries the ones that failed.
+///
+/// # Errors
+/// If the history cannot be read or there is no run to undo. The episodes that could not be reverted are returned as [`Failure`]s instead.
+///
+/// # Example
+/// ```
+/// let (run, amount, failures) = undo_run(None).await?;
+/// ```
+pub async fn undo_run(run: Option<i64>) -> anyhow::Result<(i64, usize, Vec<Failure>)> {
+    let db = open_history().await?;
+    let runs = select_runs(&db).await?;
+    let run = match run {
+        Some(run) => runs.into_iter().find(|x| x.id == run),
+        None => runs.into_iter().rev().find(|x| !x.undone),
+    };
+    let Some(run) = run else {
+        anyhow::bail!("There is no run left to undo.");
+    };
+    if run.undone {
+        anyhow::bail!("Run {} was already undone.", run.id);
+    }
+    let transfer_mode: TransferMode = serde_json::from_value(run.transfer_mode.into())?;
+    let mut reverted = 0;
+    let mut failures = Vec::new();
+    let episodes = sqlx::query_as::<_, Episode>(
+        "SELECT series_name, season, episode, old_path, new_path FROM run_episodes WHERE run_id = ? AND undone = 0 ORDER BY position DESC;",
+    )
+    .bind(run.id)
+    .fetch_all(&db)
+    .await?;
+    for episode in &episodes {
+        let old_path = Path::new(&episode.old_path);
+        let new_path = Path::new(&episode.new_path);
+        let result = match transfer_mode {
+            TransferMode::Move => match old_path.parent() {
+                Some(parent) => std::fs::create_dir_all(parent).map_err(anyhow::Error::from),
+                None => Ok(()),
+            }
+            .and_then(|_| transfer_file(new_path, old_path, TransferMode::Move)),
+            _ => std::fs::remove_file(new_path).map_err(anyhow::Error::from),
+        };
+        match result {
+            Ok(_) => {
+                for dir in new_path.ancestors().skip(1).take(2) {
+                    let _ = std::fs::remove_dir(dir);
+                }
+                sqlx::query(
+                    "UPDATE run_episodes SET undone = 1 WHERE run_id = ? AND old_path = ?;",
+                )
+                .bind(run.id)
+                .bind(&episode.old_path)
+                .execute(&db)
+                .await?;
+                reverted += 1;
+            }
+            Err(e) => failures.push(Failure::new(episode, &e)),
+        }
+    }
+    if failures.is_empty() {
+        sqlx::query("UPDATE runs SET undone = 1 WHERE id = ?;")
+            .bind(run.id)
+            .execute(&db)
+            .await?;
+    }
+    Ok((run.id, reverted, failures))
+}
+
+/// Prints every run in the history as a table, or the episodes renamed by the given run.
+///
+/// # Errors
+/// If the history cannot be read or there is no run with the given number.
+///
+/// # Example
+/// ```
+/// print_history(None).await?;
+/// ```
+pub async fn print_history(run: Option<i64>) -> anyhow::Result<()> {
+    let db = open_history().await?;
+    let mut table = match run {
+        Some(run) => tabled::Table::new(select_run_episodes(&db, run).await?),
+        None => {
+            let runs = select_runs(&db).await?;
+            if runs.is_empty() {
+                println!("There are no runs in the history yet.");
+                return Ok(());
+            }
+            tabled::Table::new(runs)
+        }
+    };
+    println!(
+        "{}",
+        table
+            .with(tabled::settings::Style::rounded())
+            .with(border_color(tabled::settings::Color::FG_GREEN))
+    );
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use crate::database::Episode;
+use crate::events::{EventKind, Events};
+use crate::files::{transfer_file, TransferMode};
+use std::path::{Path, PathBuf};
+
+/// Record of everything [`crate::files::move_files()`] changed in the file system during a run, so that it can be reverted with [`Journal::rollback()`] when the --atomic flag was given.
+///
+/// # Example
+/// ```
+/// let mut journal = Journal::new(TransferMode::Move);
+/// journal.create_dirs(Path::new("/home/user/output/Show/Season 1"))?;
+/// transfer_file(old_path, new_path, TransferMode::Move)?;
+/// journal.record(&episode);
+/// ```
+pub struct Journal {
+    mode: TransferMode,
+    created_dirs: Vec<PathBuf>,
+    transferred: Vec<Episode>,
+}
+
+impl Journal {
+    /// Creates an empty journal for files transferred with the given [`TransferMode`].
+    pub fn new(mode: TransferMode) -> Journal {
+        Journal {
+            mode,
+            created_dirs: Vec::new(),
+            transferred: Vec::new(),
+        }
+    }
+
+    /// Creates the given directory along with any missing parent directory, like [`std::fs::create_dir_all()`], recording every directory that did not exist before.
+    ///
+    /// # Errors
+    /// If one of the directories cannot be created.
+    pub fn create_dirs(&mut self, path: &Path) -> anyhow::Result<()> {
+        let mut missing = path
+            .ancestors()
+            .take_while(|x| !x.as_os_str().is_empty() && !x.exists())
+            .collect::<Vec<_>>();
+        missing.reverse();
+        for dir in missing {
+            std::fs::create_dir(dir)?;
+            self.created_dirs.push(dir.to_path_buf());
+        }
+        Ok(())
+    }
+
+    /// Records that the file of the given episode was transferred to its new_path.
+    pub fn record(&mut self, episode: &Episode) {
+        self.transferred.push(episode.clone());
+    }
+
+    /// Reverts every recorded change, from the most recent to the oldest.
+    ///
+    /// Moved files are moved back to their old_path, while copies and links are removed from their new_path. Then the directories that were created are removed.
+    /// An [`EventKind::RolledBack`] event is sent for every episode that was reverted.
+    /// Reverting does not stop at the first error, so that as much as possible is put back.
+    ///
+    /// # Errors
+    /// If any of the changes could not be reverted, listing every file or directory that was left behind, or if an event could not be sent.
+    pub fn rollback(self, events: &mut Events) -> anyhow::Result<()> {
+        let mut left_behind = Vec::new();
+        let mut event_error = None;
+        for episode in self.transferred.iter().rev() {
+            let new_path = Path::new(&episode.new_path);
+            let result = match self.mode {
+                TransferMode::Move => {
+                    transfer_file(new_path, Path::new(&episode.old_path), TransferMode::Move)
+                }
+                _ => std::fs::remove_file(new_path).map_err(anyhow::Error::from),
+            };
+            match result {
+                Ok(_) => {
+                    if let Err(e) = events.send(EventKind::RolledBack, episode, None) {
+                        event_error.get_or_insert(e);
+                    }
+                }
+                Err(e) => left_behind.push(format!("{}: {e}", episode.new_path)),
+            }
+        }
+        for dir in self.created_dirs.iter().rev() {
+            if let Err(e) = std::fs::remove_dir(dir) {
+                left_behind.push(format!("{}: {e}", dir.display()));
+            }
+        }
+        match (left_behind.is_empty(), event_error) {
+            (true, None) => Ok(()),
+            (true, Some(e)) => Err(e),
+            (false, _) => anyhow::bail!(
+                "The following could not be rolled back:\n{}",
+                left_behind.join("\n")
+            ),
+        }
+    }
+}
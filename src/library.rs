@@ -0,0 +1,115 @@
+use crate::files::Cli;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// A Plex® Media Server library the files of an input directory can be renamed to, along with the naming profile used for its episodes.
+///
+/// The libraries are the ones named in the config file by [`crate::config::load_config()`], plus the output path given with the -o flag, which is the only library when none are named.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Library {
+    /// The name given to the library in the config file, or [`None`] for the output path given with the -o flag.
+    pub name: Option<String>,
+    /// The root folder of the library, holding a folder for every series.
+    pub path: PathBuf,
+    /// The file name given to the episodes, as described in [`crate::validate::parse_template()`].
+    pub template: String,
+    /// The minimum amount of digits of the season and episode numbers in the file names.
+    pub padding: usize,
+}
+
+impl Display for Library {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{name} ({})", self.path.display()),
+            None => write!(f, "{}", self.path.display()),
+        }
+    }
+}
+
+/// Returns the index of the library with the given name, without regard to case, or at the given position of the list shown by [`library_list()`].
+///
+/// # Example
+/// ```
+/// let index = find_library(&args.libraries, "anime");
+/// ```
+pub fn find_library(libraries: &[Library], answer: &str) -> Option<usize> {
+    let answer = answer.trim();
+    libraries
+        .iter()
+        .position(|x| {
+            x.name
+                .as_ref()
+                .is_some_and(|x| x.eq_ignore_ascii_case(answer))
+        })
+        .or_else(|| answer.parse().ok().filter(|x| *x < libraries.len()))
+}
+
+/// Returns the libraries numbered one per line, as shown when asking which library an input directory should be renamed to.
+pub fn library_list(libraries: &[Library]) -> String {
+    libraries
+        .iter()
+        .enumerate()
+        .map(|(index, library)| format!("{index}. {library}\n"))
+        .collect()
+}
+
+/// Returns the library of every input directory that was chosen with the --library flag, or [`None`] for the directories the user has to be asked about.
+///
+/// The flag can be given once to use the same library for every input directory, or once per input directory in the same order as the -i flag.
+/// Without the flag, the only library is used if there is just one.
+///
+/// # Errors
+/// If the amount of libraries given does not match the amount of input directories, or if a library is not in the config file.
+///
+/// # Example
+/// ```
+/// let routes = route_libraries(&args)?;
+/// ```
+pub fn route_libraries(args: &Cli) -> anyhow::Result<Vec<Option<usize>>> {
+    let directories = args.input_paths.len();
+    let names = match args.library.len() {
+        0 => {
+            let only = match args.libraries.len() {
+                1 => Some(0),
+                _ => None,
+            };
+            return Ok(vec![only; directories]);
+        }
+        1 => vec![&args.library[0]; directories],
+        amount if amount == directories => args.library.iter().collect(),
+        amount => anyhow::bail!(
+            "{amount} libraries were given for {directories} input directories, give either one library for every directory or a single library for all of them."
+        ),
+    };
+    names
+        .into_iter()
+        .map(|name| {
+            match args.libraries.iter().position(|x| {
+                x.name
+                    .as_ref()
+                    .is_some_and(|x| x.eq_ignore_ascii_case(name))
+            }) {
+                Some(index) => Ok(Some(index)),
+                None => anyhow::bail!(
+                    "There is no library named {name} in the config file, the libraries are:\n{}",
+                    library_list(&args.libraries)
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Returns the library the given new path is in, which is the one with the longest path containing it.
+///
+/// Used to find the naming profile of an episode that is already in the database, such as when it is edited with [`crate::files::apply_edit()`].
+///
+/// # Example
+/// ```
+/// let library = library_of(&args.libraries, Path::new(&episode.new_path));
+/// ```
+pub fn library_of<'a>(libraries: &'a [Library], new_path: &Path) -> Option<&'a Library> {
+    libraries
+        .iter()
+        .filter(|x| new_path.starts_with(&x.path))
+        .max_by_key(|x| x.path.components().count())
+}
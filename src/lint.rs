@@ -0,0 +1,284 @@
+use crate::files::Cli;
+use crate::library::Library;
+use crate::preflight::Problem;
+use colored::*;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Exit code used by `rplexfmtr lint` when problems are found.
+pub const LINT_FAILURE_EXIT_CODE: i32 = 1;
+
+/// Returns a regular expression matching the file names that the template of the given library gives to the episodes of a series and season, capturing the episode number and the extension.
+///
+/// Anything after the template is allowed as long as it starts with a `.` that is not followed by a space, so that both `Show S1E2.mkv` and subtitles such as `Show S1E2.en.srt` match.
+/// The title of the episode is optional, along with the text before its placeholder, or ` - ` if the template has none, since [`crate::files::episode_path()`] leaves it out for episodes without a title.
+fn template_regex(library: &Library, series_name: &str, season: u32) -> Regex {
+    let mut pattern = "^".to_owned();
+    let mut rest = library.template.as_str();
+    let mut captured = false;
+    while let Some(start) = rest.find('{') {
+        let literal = regex::escape(&rest[..start]);
+        let end = start + rest[start..].find('}').unwrap();
+        pattern += &match (&rest[start + 1..end], captured) {
+            ("series", _) => literal + &regex::escape(series_name),
+            ("season", _) => literal + &format!("0*{season}"),
+            ("title", _) => format!("(?:{literal}.+?)?"),
+            (_, true) => literal + r"\d+",
+            (_, false) => {
+                captured = true;
+                literal + r"(?P<episode>\d+)"
+            }
+        };
+        rest = &rest[end + 1..];
+    }
+    pattern += &regex::escape(rest);
+    if !library.template.contains("{title}") {
+        pattern += "(?: - .+?)?";
+    }
+    pattern += r"(?P<suffix>\.[^ ].*)$";
+    Regex::new(&pattern).unwrap()
+}
+
+/// Returns the season number of a season folder, named `Season <number>` or `Specials` for season 0.
+fn season_number(folder: &str) -> Option<u32> {
+    match folder {
+        "Specials" => Some(0),
+        _ => folder.strip_prefix("Season ")?.parse().ok(),
+    }
+}
+
+/// Returns the names of the entries of a directory, split into folders and files and naturally sorted.
+fn entries(dir: &Path) -> std::io::Result<(Vec<String>, Vec<String>)> {
+    let mut folders = Vec::new();
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        match entry.file_type()?.is_dir() {
+            true => folders.push(name),
+            false => files.push(name),
+        }
+    }
+    folders.sort_by(|a, b| natord::compare(a, b));
+    files.sort_by(|a, b| natord::compare(a, b));
+    Ok((folders, files))
+}
+
+/// Checks that the files of the given library follow the Plex® Media Server folder structure for TV Shows and the naming template of the library, returning every problem found.
+///
+/// The following is reported:
+/// - Files that are not inside of a series and season folder.
+/// - Folders inside of a series folder that are not named `Season <number>` or `Specials`, and folders inside of a season folder.
+/// - Files whose names do not follow the template of the library for their series and season, as given by [`Library::template`].
+/// - Episode numbers used by more than one file of a season with the same extension.
+/// - Empty series and season folders.
+///
+/// If extensions are given, such as the ones of the --extensions flag, files with other extensions are not checked.
+///
+/// # Errors
+/// If a folder of the library cannot be read.
+///
+/// # Example
+/// ```
+/// for problem in lint(&args.libraries[0], &args.extensions)? {
+///     println!("{problem}");
+/// }
+/// ```
+pub fn lint(library: &Library, extensions: &[String]) -> anyhow::Result<Vec<Problem>> {
+    let mut problems = Vec::new();
+    let mut problem = |path: &Path, reason: &str| {
+        problems.push(Problem {
+            path: path.to_path_buf(),
+            reason: reason.to_owned(),
+        })
+    };
+    let checked = |file: &str| {
+        extensions.is_empty()
+            || Path::new(file).extension().is_some_and(|e| {
+                extensions
+                    .iter()
+                    .any(|x| e.eq_ignore_ascii_case(x.trim_start_matches('.')))
+            })
+    };
+    let (series, files) = entries(&library.path)?;
+    for file in files.iter().filter(|x| checked(x)) {
+        problem(
+            &library.path.join(file),
+            "file is not inside of a series folder",
+        );
+    }
+    for series_name in series {
+        let series_path = library.path.join(&series_name);
+        let (seasons, files) = entries(&series_path)?;
+        if seasons.is_empty() && files.is_empty() {
+            problem(&series_path, "empty folder");
+        }
+        for file in files.iter().filter(|x| checked(x)) {
+            problem(
+                &series_path.join(file),
+                "file is not inside of a season folder",
+            );
+        }
+        for season_name in seasons {
+            let season_path = series_path.join(&season_name);
+            let Some(season) = season_number(&season_name) else {
+                problem(
+                    &season_path,
+                    "folder is not named Season <number> or Specials",
+                );
+                continue;
+            };
+            let (folders, files) = entries(&season_path)?;
+            if folders.is_empty() && files.is_empty() {
+                problem(&season_path, "empty folder");
+            }
+            for folder in folders {
+                problem(
+                    &season_path.join(folder),
+                    "folder inside of a season folder",
+                );
+            }
+            let regex = template_regex(library, &series_name, season);
+            let mut numbers: HashMap<(u32, String), String> = HashMap::new();
+            for file in files.iter().filter(|x| checked(x)) {
+                let path = season_path.join(file);
+                let Some((episode, suffix)) = regex.captures(file).and_then(|x| {
+                    Some((
+                        x.name("episode")?.as_str().parse::<u32>().ok()?,
+                        x["suffix"].to_lowercase(),
+                    ))
+                }) else {
+                    problem(
+                        &path,
+                        &format!("name does not follow the template {}", library.template),
+                    );
+                    continue;
+                };
+                if let Some(other) = numbers.insert((episode, suffix), file.clone()) {
+                    problem(&path, &format!("episode {episode} is also used by {other}"));
+                }
+            }
+        }
+    }
+    Ok(problems)
+}
+
+/// Checks the given library folders with [`lint()`], or every library of the config file if none are given, printing the problems found and returning how many there are.
+///
+/// A folder that is the path of a library of the config file is checked with the naming template of that library, while any other folder is checked with the template given with the --template flag.
+///
+/// # Errors
+/// If no folders are given and there are no libraries, or if a folder cannot be read.
+///
+/// # Example
+/// ```
+/// if print_lint(&args, &paths)? > 0 {
+///     exit(LINT_FAILURE_EXIT_CODE);
+/// }
+/// ```
+pub fn print_lint(args: &Cli, paths: &[PathBuf]) -> anyhow::Result<usize> {
+    let libraries = match paths.is_empty() {
+        true => args.libraries.clone(),
+        false => paths
+            .iter()
+            .map(
+                |path| match args.libraries.iter().find(|x| &x.path == path) {
+                    Some(library) => library.clone(),
+                    None => Library {
+                        name: None,
+                        path: path.clone(),
+                        template: args.template().to_owned(),
+                        padding: args.padding(),
+                    },
+                },
+            )
+            .collect(),
+    };
+    if libraries.is_empty() {
+        anyhow::bail!("Give the library folders to check, or name libraries in the config file.");
+    }
+    let mut amount = 0;
+    for library in &libraries {
+        let problems = lint(library, &args.extensions)?;
+        match problems.is_empty() {
+            true => println!(
+                "{} follows the naming of Plex.",
+                library.to_string().green()
+            ),
+            false => {
+                println!(
+                    "{} problems were found in {}:",
+                    problems.len().to_string().red(),
+                    library.to_string().yellow()
+                );
+                for problem in &problems {
+                    println!("  {}", problem.to_string().red());
+                }
+            }
+        }
+        amount += problems.len();
+    }
+    Ok(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(template: &str) -> Library {
+        Library {
+            name: None,
+            path: PathBuf::from("/library"),
+            template: template.to_owned(),
+            padding: 0,
+        }
+    }
+
+    fn episode(regex: &Regex, file_name: &str) -> Option<u32> {
+        regex
+            .captures(file_name)
+            .map(|x| x["episode"].parse().unwrap())
+    }
+
+    #[test]
+    fn template_regex_matches_the_default_template() {
+        let regex = template_regex(&library("{series} S{season}E{episode}"), "Show", 1);
+        assert_eq!(episode(&regex, "Show S1E2.mkv"), Some(2));
+        assert_eq!(episode(&regex, "Show S01E02.en.srt"), Some(2));
+        assert_eq!(episode(&regex, "Show S1E2 - Pilot.mkv"), Some(2));
+        assert_eq!(episode(&regex, "Show S2E2.mkv"), None);
+        assert_eq!(episode(&regex, "Other S1E2.mkv"), None);
+        assert_eq!(episode(&regex, "Show S1E2"), None);
+        assert_eq!(episode(&regex, "Show S1E2. mkv"), None);
+    }
+
+    #[test]
+    fn template_regex_makes_the_title_optional() {
+        let regex = template_regex(
+            &library("{series} - S{season}E{episode} - {title}"),
+            "Show",
+            3,
+        );
+        assert_eq!(episode(&regex, "Show - S3E4 - Pilot.mkv"), Some(4));
+        assert_eq!(episode(&regex, "Show - S03E04.mkv"), Some(4));
+        assert_eq!(episode(&regex, "Show - S3E4 - .mkv"), None);
+    }
+
+    #[test]
+    fn template_regex_escapes_the_series_name() {
+        let regex = template_regex(
+            &library("{series} S{season}E{episode}"),
+            "Mr. Robot (2015)",
+            1,
+        );
+        assert_eq!(episode(&regex, "Mr. Robot (2015) S1E1.mkv"), Some(1));
+        assert_eq!(episode(&regex, "Mrs Robot (2015) S1E1.mkv"), None);
+    }
+
+    #[test]
+    fn template_regex_captures_the_first_episode_placeholder() {
+        let regex = template_regex(&library("{episode} {series} {episode}"), "Show", 1);
+        assert_eq!(episode(&regex, "7 Show 8.mkv"), Some(7));
+    }
+}
@@ -10,150 +10,283 @@
 //! # Usage
 //! plexfmtr -i \[input_folder(s)\] -o \[output_folder\]
 
+/// Contains the removal of the input directories left behind once the files are renamed
+pub mod cleanup;
+/// Contains the loading of the config file holding the default values of the flags
+pub mod config;
 /// Holds the all sqlite database related functions and structs
 pub mod database;
+/// Contains the events streamed while the files are transferred
+pub mod events;
 /// Contains all file system manipulation and display functions and structs as well as command line argument and path parsing.
 pub mod files;
+/// Contains the persistent history of the runs, used to undo them
+pub mod history;
+/// Contains the record of the changes made to the file system, used to revert them
+pub mod journal;
+/// Contains the libraries the input directories are renamed to
+pub mod library;
+/// Contains the checks of the naming of the files already in the libraries
+pub mod lint;
+/// Contains the mapping of file names to episodes with a regular expression typed at the selection step
+pub mod mapping;
+/// Contains the local store of show metadata imported from episode listings
+pub mod metadata;
+/// Contains the full screen file picker used as an alternative to typing a range
+pub mod picker;
+/// Contains the functions to save and load the planned changes to and from a plan file
+pub mod plan;
+/// Contains the checks made before the files are transferred
+pub mod preflight;
+/// Contains the line editor used to read the answers to the prompts
+pub mod prompt;
+/// Contains the rules matching file names to episodes without prompting
+pub mod rules;
+/// Contains the listing of the files of input directories along with the episodes guessed from their names
+pub mod scan;
+/// Contains the generation of shell scripts equivalent to the planned changes
+pub mod script;
+/// Contains the episode titles read from episode lists
+pub mod titles;
+/// Contains the terminal user interface used as an alternative to the prompts
+pub mod tui;
 /// Contains all functions to validate user input
 pub mod validate;
-use clap::Parser;
+/// Contains the watching of drop folders for new downloads to rename
+pub mod watch;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches};
+use cleanup::*;
 use colored::*;
+use config::*;
 use database::*;
+use events::*;
 use files::*;
-use std::{io, println, process::exit};
+use history::*;
+use journal::*;
+use library::*;
+use lint::*;
+use metadata::*;
+use plan::*;
+use preflight::*;
+use prompt::*;
+use rules::*;
+use scan::*;
+use script::*;
+use std::path::Path;
+use std::time::Duration;
+use std::{println, process::exit};
+use tui::*;
 use validate::*;
+use watch::*;
 
 /// The main function for rplexfmtr.\
 ///
 /// First, the main function  initialized the transient, in memory, database using [`database::setup_database()`].
-/// Then parses and verifies command line arguments using [`clap`] and [`validate::valid_paths()`].\
+/// Then parses and verifies command line arguments using [`clap`] and [`validate::valid_paths()`], and fills the flags that were not given with the settings of the config file using [`config::load_config()`].
+/// The output path is only required once the config file is loaded, since it can be given there instead of with the -o flag, and is not needed at all if the config file names libraries.\
 ///
-/// Then iterates through the input paths and prompts the user for what series name would they like the files to correspond to.
-/// If the user inputs an invalid name, it will prompt the user again to enter a name until a valid name is given.\
+/// The arguments are parsed as a [`files::Rplexfmtr`], whose [`files::Command`] decides what is run. Without a command, or with the `rename` command, the files are renamed as follows, while the other commands are run right away:
+/// - `history`: The past runs are printed with [`history::print_history()`].
+/// - `undo`: A past run is put back with [`history::undo_run()`], exiting with [`files::PARTIAL_FAILURE_EXIT_CODE`] if some files could not be put back.
+/// - `lint`: The libraries are checked with [`lint::print_lint()`], exiting with [`lint::LINT_FAILURE_EXIT_CODE`] if any problem is found.
+/// - `scan`: The files of the given folders are listed with [`scan::print_scan()`].
+/// - `watch`: The given drop folders, or the ones of the config file, are watched with [`watch::watch()`] until SIGINT or SIGTERM is received.
+/// - `import-metadata`: The given metadata dumps are imported with [`metadata::import_metadata()`].
 ///
-/// Once a valid name is provided, the user is prompted to choose which files they would like to be renamed.
-/// Once again, if the user provides an invalid input, they will be prompted to input a season number again unit a valid input is provided.\
+/// If the resume command was given, the episodes left over by the last run are loaded from [`plan::progress_path()`] as if it was given with the --plan-in flag.
+/// Unless resuming or only writing a script with the --emit-script flag, nothing is prompted or loaded while the last run has files left over, as checked with [`plan::check_progress()`].
+/// If the --plan-in flag was given, the episodes of the plan file are loaded with [`plan::load_plan()`] and executed right away without prompting.
+/// The transfer mode saved in the plan is used unless the -t flag was given on the command line, and always when resuming.
+/// Else, the user is asked how the files should be renamed with [`prompt::run_prompts()`], or with [`tui::run_tui()`] if the --tui flag was given.\
 ///
-/// Then, the validated set of files will be inputted to the database.
-/// If more than one input directory was provided, the user will be prompted to input the information for that set of files, repeating the process.\
+/// If the --plan-out flag was given, the planned changes are then saved with [`plan::save_plan()`], whether or not the user chose to execute them.
+/// If the --emit-script flag was given, the planned changes are written to a shell script and an undo script with [`script::emit_scripts()`] and no files are renamed.\
 ///
-/// Once all input directories have been processed, the user will be prompted to preview the changes.
-/// If the user inputs 'y', a table containing the series_name, season, episode, current_path and new_path will be displayed to the user.
-/// Else, it does not display the changes.\
-///
-/// Finally, the user will be prompted if they would like to execute the changes.
-/// If the user inputs 'y', the renaming process will commence and the files will be renamed and moved to the output directory following the Plex® Media Server folder structure.
-/// If all files are renamed successfully, the user will be shown that the files have been moved successfully and show the the location of the renamed files.
+/// If the user chose to execute the changes, they are first checked with [`preflight::preflight()`], and no file is renamed if any problem is found unless the --keep-going flag was given.
+/// Then, the files will be transferred to the output directory following the Plex® Media Server folder structure with [`files::move_files()`].
+/// If SIGINT or SIGTERM is received while they are transferred, the amount of files left over is shown and rplexfmtr exits with a status code of `130`.
+/// If all files are renamed successfully, the user will be shown that the files have been moved successfully and show the the location of the renamed files, once for every [`library::Library`] they were renamed to.
+/// If the --keep-going flag was given and some of the files could not be renamed, a table of the failures is shown as well and rplexfmtr exits with [`files::PARTIAL_FAILURE_EXIT_CODE`].
+/// If the --cleanup flag was given and every file was renamed, the input directories left empty or holding only junk files are then removed with [`cleanup::clean_up()`].
+/// These messages are printed to standard error instead of standard output when events are streamed to standard output with the --events flag.
 #[tokio::main]
 pub async fn main() -> anyhow::Result<()> {
     let db = setup_database(URL).await?;
-    let args = Cli::parse();
-    for path in &args.input_paths {
-        let name: String;
-        loop {
+    let matches = Rplexfmtr::command().get_matches();
+    let Rplexfmtr { command, args } =
+        Rplexfmtr::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let (command, mut args) = match command {
+        Some(Command::Rename(args)) => (None, *args),
+        command => (command, args),
+    };
+    let matches = matches.subcommand().map_or(&matches, |(_, x)| x);
+    args.transfer_given = matches.value_source("transfer") == Some(ValueSource::CommandLine);
+    load_config(&mut args)?;
+    match command {
+        Some(Command::History { run }) => return print_history(run).await,
+        Some(Command::Undo { run }) => {
+            let (run, reverted, failures) = undo_run(run).await?;
             println!(
-                "What would you like the entries for {} to be titled?: ",
-                path.to_str().unwrap().green()
+                "Run {} undone, {} files were put back.",
+                run.to_string().green(),
+                reverted.to_string().green()
             );
-            let mut ans = String::new();
-            io::stdin().read_line(&mut ans)?;
-            ans = String::from(ans.trim_end());
-            if !valid_name(&ans) {
-                continue;
-            } else {
-                name = ans;
-                break;
+            if !failures.is_empty() {
+                println!("{}", failure_report(&failures, reverted + failures.len()));
+                exit(PARTIAL_FAILURE_EXIT_CODE);
             }
+            return Ok(());
         }
-        let files = get_files(path.to_owned())?;
-        print_directory(path.to_path_buf())?;
-        println!("Which files would you like to choose?");
-        let mut selection: String = String::new();
-        io::stdin().read_line(&mut selection)?;
-        selection = String::from(selection.trim_end());
-        let files_numbers = parse_range(files.len(), selection)?;
-        let mut selected_files: Vec<_> = Vec::new();
-        for (index, file) in files.into_iter().enumerate() {
-            if files_numbers.contains(&index) {
-                selected_files.push(file);
+        Some(Command::Lint { paths }) => {
+            if print_lint(&args, &paths)? > 0 {
+                exit(LINT_FAILURE_EXIT_CODE);
             }
+            return Ok(());
         }
-        selected_files.sort_by(|a, b| {
-            natord::compare(
-                a.file_name().to_ascii_lowercase().to_str().unwrap(),
-                b.file_name().to_ascii_lowercase().to_str().unwrap(),
-            )
-        });
-        let season: u32;
-        loop {
-            println!("What season do these files belong to?");
-            let mut ans: String = String::new();
-            io::stdin().read_line(&mut ans)?;
-            ans = String::from(ans.trim_end());
-            break match ans.parse::<u32>() {
-                Ok(x) => {
-                    season = x;
-                }
-                Err(_) => {
-                    continue;
-                }
+        Some(Command::Scan { paths }) => return print_scan(&args, &paths),
+        Some(Command::Watch {
+            paths,
+            library,
+            series,
+            settle,
+        }) => {
+            let folders = match paths.is_empty() {
+                true => args.watch_folders.clone(),
+                false => paths
+                    .into_iter()
+                    .map(|path| {
+                        Ok(WatchFolder {
+                            path: std::fs::canonicalize(path)?,
+                            library: library.clone(),
+                            series: series.clone(),
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?,
             };
+            return watch(&db, &args, &folders, Duration::from_secs(settle)).await;
         }
-
-        for (index, file) in selected_files.into_iter().enumerate() {
-            insert_episode(
-                &db,
-                &name,
-                season,
-                (index as u32) + 1,
-                file.path(),
-                args.output_path
-                    .to_owned()
-                    .join(&name)
-                    .join("Season ".to_owned() + &season.to_string())
-                    .join(
-                        name.clone()
-                            + " S"
-                            + &season.to_string()
-                            + "E"
-                            + &(index + 1).to_string()
-                            + "."
-                            + file
-                                .file_name()
-                                .to_str()
-                                .unwrap()
-                                .split('.')
-                                .collect::<Vec<_>>()
-                                .last()
-                                .unwrap(),
-                    ),
+        Some(Command::ImportMetadata { files, show }) => {
+            return import_metadata(&files, show.as_deref()).await
+        }
+        Some(Command::Resume) => {
+            let path = progress_path()?;
+            if !path.exists() {
+                anyhow::bail!("There is no interrupted run to resume.");
+            }
+            args.plan_in = Some(path);
+        }
+        Some(Command::Rename(_)) | None => {}
+    }
+    if command.is_none() && args.plan_in.is_none() && args.libraries.is_empty() {
+        Rplexfmtr::command()
+            .error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "the output path must be given with --output-path, RPLEXFMTR_OUTPUT or the output_path setting of the config file, unless libraries are named in the config file",
             )
-            .await?;
+            .exit();
+    }
+    if args.emit_script.is_none()
+        && !progress_path().is_ok_and(|x| args.plan_in.as_ref() == Some(&x))
+    {
+        check_progress()?;
+    }
+    let execute = match &args.plan_in {
+        Some(plan_in) => {
+            let transfer_mode = load_plan(&db, plan_in).await?;
+            if !args.transfer_given || matches!(command, Some(Command::Resume)) {
+                args.transfer = Some(transfer_mode);
+            }
+            true
         }
-        clearscreen::clear()?;
+        None => match args.tui {
+            true => run_tui(&db, &args).await?,
+            false => run_prompts(&db, &args).await?,
+        },
+    };
+    let mut messages = Vec::new();
+    let mut exit_code = 0;
+    if let Some(plan_out) = &args.plan_out {
+        save_plan(&db, plan_out, args.transfer_mode()).await?;
+        messages.push(format!(
+            "Plan saved to {}.",
+            plan_out.to_str().unwrap().green()
+        ));
     }
-    println!("Would you like to preview the changes [y/n]:");
-    let mut ans: String = String::new();
-    io::stdin().read_line(&mut ans)?;
-    ans = String::from(ans.trim_end());
-    match ans.to_lowercase() == "y" {
-        true => {
-            preview_changes(&db).await?;
+    if let Some(script) = &args.emit_script {
+        let undo_script = emit_scripts(&db, script, args.transfer_mode()).await?;
+        messages.push(format!(
+            "Script saved to {} and undo script saved to {}, no files were renamed.",
+            script.to_str().unwrap().green(),
+            undo_script.to_str().unwrap().green()
+        ));
+    } else if execute {
+        if !args.keep_going {
+            let preflight = preflight(&select_plan(&db).await?, args.transfer_mode());
+            if !preflight.problems.is_empty() {
+                anyhow::bail!(
+                    "{}No files were renamed. Fix the problems above, or pass --keep-going to rename the rest of the files anyway.",
+                    preflight.report()
+                );
+            }
+        }
+        let failures = match move_files(&db, &args).await? {
+            Outcome::Finished(failures) => failures,
+            Outcome::Interrupted(remaining) => {
+                match (args.atomic, remaining) {
+                    (true, 0) => {
+                        eprintln!("Interrupted, every file renamed in this run was put back.")
+                    }
+                    (_, remaining) => eprintln!(
+                        "Interrupted, {} files were not renamed. Run {} to rename them.",
+                        remaining.to_string().yellow(),
+                        "rplexfmtr resume".yellow()
+                    ),
+                }
+                exit(130);
+            }
+        };
+        if !failures.is_empty() {
+            let amount_episodes = select_plan(&db).await?.len();
+            messages.push(failure_report(&failures, amount_episodes));
+            exit_code = PARTIAL_FAILURE_EXIT_CODE;
+        } else {
+            match &args.plan_in {
+                Some(plan_in) => messages.push(format!(
+                    "Files from {} renamed successfully.",
+                    plan_in.to_str().unwrap().green()
+                )),
+                None => {
+                    let episodes = select_plan(&db).await?;
+                    for library in args.libraries.iter().filter(|library| {
+                        episodes.iter().any(|x| {
+                            library_of(&args.libraries, Path::new(&x.new_path)) == Some(library)
+                        })
+                    }) {
+                        messages.push(format!(
+                            "Files renamed successfully, Located at {}.",
+                            library.to_string().green()
+                        ));
+                    }
+                }
+            }
+            if args.cleanup {
+                let episodes = select_plan(&db).await?;
+                let cleanups = plan_cleanup(&cleanup_dirs(&args, &episodes), &episodes, &args);
+                for dir in clean_up(&cleanups, &args)? {
+                    messages.push(format!("Removed {}.", dir.to_str().unwrap().yellow()));
+                }
+            }
         }
-        false => {}
     }
-    println!("Would you like to execute these changes [y/n]:");
-    let mut ans: String = String::new();
-    io::stdin().read_line(&mut ans)?;
-    ans = String::from(ans.trim_end());
-    match ans.to_ascii_lowercase() == "y" {
-        true => move_files(&db, &args).await?,
-        false => exit(0),
+    for message in messages {
+        match args.events_on_stdout() {
+            true => eprintln!("{message}"),
+            false => println!("{message}"),
+        }
+    }
+    if exit_code != 0 {
+        exit(exit_code);
     }
-
-    println!(
-        "Files renamed successfully, Located at {}.",
-        args.output_path.to_str().unwrap().green()
-    );
     Ok(())
 }
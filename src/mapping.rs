@@ -0,0 +1,236 @@
+use crate::titles::sanitize_title;
+use colored::*;
+use regex::Regex;
+
+/// Prefix of the answers to the selection step that are a [`Mapping`] instead of a range, such as `re:E(?P<episode>\d+)`.
+pub const MAPPING_PREFIX: &str = "re:";
+
+/// A regular expression typed at the selection step, whose named groups give the season, episode and title of each file instead of its position in the selection.
+///
+/// The `episode` group is required, while the `season` and `title` groups are optional. Files without a season are given the season answered at the season prompt.
+#[derive(Clone, Debug)]
+pub struct Mapping {
+    /// The compiled regular expression.
+    pub regex: Regex,
+}
+
+/// The season, episode and title a file name was parsed to by a [`Mapping`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Parsed {
+    /// The season captured by the `season` group, if the mapping has one.
+    pub season: Option<u32>,
+    /// The episode captured by the `episode` group.
+    pub episode: u32,
+    /// The title captured by the `title` group, if the mapping has one, with dots and underscores replaced with spaces and sanitized with [`sanitize_title()`].
+    pub title: Option<String>,
+}
+
+/// Returns the [`Mapping`] given by an answer to the selection step that starts with [`MAPPING_PREFIX`], or [`None`] if the answer is a range instead.
+///
+/// # Errors
+/// If the regular expression is invalid or has no `episode` group, the reason is returned as the error.
+///
+/// # Example
+/// ```
+/// let mapping = parse_mapping(r"re:S(?P<season>\d+)E(?P<episode>\d+)").unwrap()?;
+/// assert!(mapping.has_season());
+/// ```
+pub fn parse_mapping(answer: &str) -> Option<Result<Mapping, String>> {
+    let pattern = answer.trim().strip_prefix(MAPPING_PREFIX)?;
+    Some(match Regex::new(pattern) {
+        Ok(regex) if regex.capture_names().flatten().any(|x| x == "episode") => {
+            Ok(Mapping { regex })
+        }
+        Ok(_) => Err("the regular expression has no (?P<episode>...) group".to_owned()),
+        Err(e) => Err(e.to_string()),
+    })
+}
+
+impl Mapping {
+    /// True if the regular expression has a `season` group, in which case the season prompt is only needed for the files it does not capture a season for.
+    pub fn has_season(&self) -> bool {
+        self.regex.capture_names().flatten().any(|x| x == "season")
+    }
+
+    /// Parses a file name with the regular expression, returning [`None`] if it does not match or its groups do not capture numbers.
+    ///
+    /// # Example
+    /// ```
+    /// let parsed = mapping.parse("Show - 1x02 - Pilot.mkv").unwrap();
+    /// assert_eq!(parsed.episode, 2);
+    /// ```
+    pub fn parse(&self, file_name: &str) -> Option<Parsed> {
+        let captures = self.regex.captures(file_name)?;
+        Some(Parsed {
+            season: match captures.name("season") {
+                Some(season) => Some(season.as_str().parse().ok()?),
+                None => None,
+            },
+            episode: captures.name("episode")?.as_str().parse().ok()?,
+            title: captures
+                .name("title")
+                .and_then(|x| sanitize_title(&x.as_str().replace(['.', '_'], " "))),
+        })
+    }
+
+    /// Parses every file name with [`Mapping::parse()`], in the order given.
+    ///
+    /// Files that do not match, or that are parsed to the same season and episode as an earlier file, are returned as an error holding the reason, so they can be shown in the preview and left out of the selection.
+    /// Since the season of a file parsed without one is only answered later, it is taken to be the same as the season of any other file with the same episode.
+    ///
+    /// # Example
+    /// ```
+    /// for (name, parsed) in names.iter().zip(mapping.map_files(&names)) {
+    ///     println!("{name}: {parsed:?}");
+    /// }
+    /// ```
+    pub fn map_files(&self, names: &[String]) -> Vec<Result<Parsed, String>> {
+        let mut used: Vec<(Option<u32>, u32, usize)> = Vec::new();
+        names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let parsed = self.parse(name).ok_or("not matched".to_owned())?;
+                let other = used.iter().find(|(season, episode, _)| {
+                    *episode == parsed.episode
+                        && (season.is_none() || parsed.season.is_none() || *season == parsed.season)
+                });
+                match other {
+                    Some((_, _, other)) => Err(format!("same episode as file {other}")),
+                    None => {
+                        used.push((parsed.season, parsed.episode, index));
+                        Ok(parsed)
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Parsed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.season {
+            Some(season) => write!(f, "season {season}, episode {}", self.episode)?,
+            None => write!(f, "episode {}", self.episode)?,
+        }
+        match &self.title {
+            Some(title) => write!(f, ", {title}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns what every file was parsed to by [`Mapping::map_files()`], one numbered file per line, as shown at the selection step before the mapping is used.
+///
+/// # Example
+/// ```
+/// print!("{}", mapping_preview(&names, &mapping.map_files(&names)));
+/// ```
+pub fn mapping_preview(names: &[String], parsed: &[Result<Parsed, String>]) -> String {
+    names
+        .iter()
+        .zip(parsed)
+        .enumerate()
+        .map(|(index, (name, parsed))| {
+            let result = match parsed {
+                Ok(parsed) => parsed.to_string().green(),
+                Err(reason) => reason.red(),
+            };
+            format!("{index}. {name} → {result}\n")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(answer: &str) -> Mapping {
+        parse_mapping(answer).unwrap().unwrap()
+    }
+
+    fn parsed(season: Option<u32>, episode: u32, title: Option<&str>) -> Parsed {
+        Parsed {
+            season,
+            episode,
+            title: title.map(|x| x.to_owned()),
+        }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_mapping_needs_the_prefix_and_an_episode_group() {
+        assert!(parse_mapping("0-3").is_none());
+        assert!(parse_mapping(r" re:E(?P<episode>\d+) ").unwrap().is_ok());
+        assert!(parse_mapping(r"re:E(\d+)").unwrap().is_err());
+        assert!(parse_mapping(r"re:E(?P<episode>\d+").unwrap().is_err());
+    }
+
+    #[test]
+    fn parse_reads_the_groups() {
+        assert!(!mapping(r"re:E(?P<episode>\d+)").has_season());
+        let mapping = mapping(r"re:S(?P<season>\d+)E(?P<episode>\d+)\.(?P<title>.+)\.mkv");
+        assert!(mapping.has_season());
+        assert_eq!(
+            mapping.parse("Show.S01E02.The_Pilot.Part.1.mkv"),
+            Some(parsed(Some(1), 2, Some("The Pilot Part 1")))
+        );
+        assert_eq!(mapping.parse("Show.E02.mkv"), None);
+    }
+
+    #[test]
+    fn parse_needs_numbers() {
+        let mapping = mapping(r"re:E(?P<episode>\w+)");
+        assert_eq!(mapping.parse("Show E07.mkv"), Some(parsed(None, 7, None)));
+        assert_eq!(mapping.parse("Show Ebonus.mkv"), None);
+    }
+
+    #[test]
+    fn map_files_reports_unmatched_and_duplicate_files() {
+        let mapping = mapping(r"re:S(?P<season>\d+)E(?P<episode>\d+)");
+        let results = mapping.map_files(&names(&[
+            "S01E01.mkv",
+            "extras.mkv",
+            "S02E01.mkv",
+            "S01E01.v2.mkv",
+        ]));
+        assert_eq!(
+            results,
+            vec![
+                Ok(parsed(Some(1), 1, None)),
+                Err("not matched".to_owned()),
+                Ok(parsed(Some(2), 1, None)),
+                Err("same episode as file 0".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn map_files_treats_a_missing_season_as_any_season() {
+        let mapping = mapping(r"re:(?:S(?P<season>\d+))?E(?P<episode>\d+)");
+        let results = mapping.map_files(&names(&[
+            "S01E02.mkv",
+            "E02.mkv",
+            "E03.mkv",
+            "S02E03.mkv",
+            "S02E02.mkv",
+            "S03E04.mkv",
+            "S04E04.mkv",
+        ]));
+        assert_eq!(
+            results,
+            vec![
+                Ok(parsed(Some(1), 2, None)),
+                Err("same episode as file 0".to_owned()),
+                Ok(parsed(None, 3, None)),
+                Err("same episode as file 2".to_owned()),
+                Ok(parsed(Some(2), 2, None)),
+                Ok(parsed(Some(3), 4, None)),
+                Ok(parsed(Some(4), 4, None)),
+            ]
+        );
+    }
+}
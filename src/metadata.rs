@@ -0,0 +1,643 @@
+use crate::titles::{sanitize_title, Titles};
+use colored::*;
+use regex::Regex;
+use serde_json::Value;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use sqlx::FromRow;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// An episode of a show read from a metadata dump.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EpisodeMetadata {
+    /// The season number, `0` for specials.
+    pub season: u32,
+    /// The episode number within the season.
+    pub episode: u32,
+    /// The title of the episode, as written in the dump.
+    pub title: Option<String>,
+}
+
+/// A show read from a metadata dump by [`parse_metadata()`], along with its episodes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShowMetadata {
+    /// The canonical name of the show.
+    pub name: String,
+    /// The year the show premiered, if the dump gives it.
+    pub year: Option<u32>,
+    /// The episodes of the show.
+    pub episodes: Vec<EpisodeMetadata>,
+}
+
+/// A show of the metadata database as returned by [`Metadata::open()`], along with how many seasons and episodes it has.
+#[derive(Clone, Debug, FromRow, PartialEq, Eq)]
+pub struct MetadataShow {
+    /// Identifier of the show in the metadata database.
+    pub id: i64,
+    /// The canonical name of the show.
+    pub name: String,
+    /// The year the show premiered, if known.
+    pub year: Option<u32>,
+    /// Amount of seasons of the show.
+    pub seasons: i64,
+    /// Amount of episodes of the show.
+    pub episodes: i64,
+}
+
+impl MetadataShow {
+    /// The name of the show followed by its year, such as `Doctor Who (2005)`, which is how Plex® Media Server tells apart shows with the same name. Just the name if the year is not known.
+    pub fn canonical_name(&self) -> String {
+        match self.year {
+            Some(year) => format!("{} ({year})", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Returns the path of the database holding the imported show metadata, `rplexfmtr/metadata.db` inside of the data directory of the user.
+///
+/// # Errors
+/// If the data directory of the user cannot be found.
+pub fn metadata_path() -> anyhow::Result<PathBuf> {
+    match dirs::data_dir() {
+        Some(dir) => Ok(dir.join("rplexfmtr").join("metadata.db")),
+        None => anyhow::bail!("Could not find the data directory to keep the show metadata."),
+    }
+}
+
+/// Opens the database at [`metadata_path()`], creating it along with its tables if it does not exist yet.
+///
+/// Like the history of [`crate::history`], this database is kept on disk, so that the shows imported with `rplexfmtr import-metadata` can be looked up while prompting without network access.
+///
+/// # Errors
+/// If the database cannot be created or opened.
+///
+/// # Example
+/// ```
+/// let db = open_metadata().await?;
+/// ```
+pub async fn open_metadata() -> anyhow::Result<SqlitePool> {
+    let path = metadata_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let db = SqlitePool::connect_with(
+        SqliteConnectOptions::from_str("sqlite:")?
+            .filename(path)
+            .create_if_missing(true),
+    )
+    .await?;
+    sqlx::query("CREATE TABLE IF NOT EXISTS shows (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, year INTEGER, source TEXT NOT NULL);")
+        .execute(&db)
+        .await?;
+    sqlx::query("CREATE TABLE IF NOT EXISTS show_episodes (show_id INTEGER NOT NULL REFERENCES shows(id), season INTEGER NOT NULL, episode INTEGER NOT NULL, title TEXT, PRIMARY KEY (show_id, season, episode));")
+        .execute(&db)
+        .await?;
+    Ok(db)
+}
+
+/// Returns the first of the given fields of a JSON object that is set.
+fn field<'a>(value: &'a Value, names: &[&str]) -> Option<&'a Value> {
+    names
+        .iter()
+        .find_map(|name| value.get(name).filter(|x| !x.is_null()))
+}
+
+/// Returns the first of the given fields of a JSON object that holds a number, or a string holding a number.
+fn number(value: &Value, names: &[&str]) -> Option<u32> {
+    match field(value, names)? {
+        Value::Number(number) => number.as_u64()?.try_into().ok(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Returns the first of the given fields of a JSON object that holds a string that is not empty.
+fn text(value: &Value, names: &[&str]) -> Option<String> {
+    field(value, names)
+        .and_then(|x| x.as_str())
+        .map(|x| x.trim().to_owned())
+        .filter(|x| !x.is_empty())
+}
+
+/// Returns the year of a show given by a year field, or taken from the date it premiered.
+fn json_year(value: &Value) -> Option<u32> {
+    number(value, &["year"]).or_else(|| {
+        text(value, &["premiered", "firstAired", "first_air_date"])?
+            .get(..4)?
+            .parse()
+            .ok()
+    })
+}
+
+/// Names of the fields holding the season number of an episode, in TVmaze, TVDB and TMDB dumps.
+const SEASON_FIELDS: [&str; 4] = ["season", "seasonNumber", "airedSeason", "season_number"];
+
+/// Reads an episode of a JSON dump, returning [`None`] if it has no season or episode number, such as the specials of TVmaze.
+fn json_episode(value: &Value) -> Option<EpisodeMetadata> {
+    Some(EpisodeMetadata {
+        season: number(value, &SEASON_FIELDS)?,
+        episode: number(
+            value,
+            &[
+                "number",
+                "episode",
+                "episodeNumber",
+                "airedEpisodeNumber",
+                "episode_number",
+            ],
+        )?,
+        title: text(value, &["name", "title", "episodeName"]),
+    })
+}
+
+/// Reads the episodes of a JSON array.
+fn json_episodes(value: Option<&Value>) -> Vec<EpisodeMetadata> {
+    value
+        .and_then(|x| x.as_array())
+        .map(|x| x.iter().filter_map(json_episode).collect())
+        .unwrap_or_default()
+}
+
+/// Reads a show of a JSON dump, whose episodes are either embedded as in TVmaze or listed next to it as in TVDB.
+fn json_show(
+    show: &Value,
+    episodes: Option<&Value>,
+    name: Option<&str>,
+) -> Result<ShowMetadata, String> {
+    Ok(ShowMetadata {
+        name: text(show, &["name", "seriesName", "title"])
+            .or(name.map(|x| x.to_owned()))
+            .ok_or("a show has no name, give it with --show")?,
+        year: json_year(show),
+        episodes: json_episodes(
+            episodes
+                .or_else(|| show.pointer("/_embedded/episodes"))
+                .or_else(|| show.get("episodes")),
+        ),
+    })
+}
+
+/// Reads the shows of a JSON dump, which can be:
+/// - A TVmaze show with embedded episodes, as returned by `/shows/<id>?embed=episodes`, or an array of them.
+/// - A TVDB response holding a series and its episodes, as returned by `/series/<id>/episodes/default`, or a series with its episodes, as returned by `/series/<id>/extended`.
+/// - An array of episodes, as returned by the TVmaze `/shows/<id>/episodes`, in which case the name of the show must be given.
+fn json_shows(value: &Value, name: Option<&str>) -> Result<Vec<ShowMetadata>, String> {
+    if let Some(data) = value.get("data") {
+        return json_shows(data, name);
+    }
+    match value {
+        Value::Array(items) if items.iter().any(|x| field(x, &SEASON_FIELDS).is_some()) => {
+            Ok(vec![ShowMetadata {
+                name: name
+                    .ok_or("the file only holds episodes, give the name of the show with --show")?
+                    .to_owned(),
+                year: None,
+                episodes: items.iter().filter_map(json_episode).collect(),
+            }])
+        }
+        Value::Array(items) => items.iter().map(|x| json_show(x, None, name)).collect(),
+        Value::Object(_) => match value.get("series") {
+            Some(series) => Ok(vec![json_show(series, value.get("episodes"), name)?]),
+            None => Ok(vec![json_show(value, None, name)?]),
+        },
+        _ => Err("the file does not hold a show or a list of episodes".to_owned()),
+    }
+}
+
+/// Reads the shows of a CSV dump, whose header row names the columns.
+///
+/// The season and episode columns are required, as `season` and `episode` or `number`. The show can be given in a `show`, `series` or `series_name` column, the year in a `year` or `premiered` column and the title in a `title` or `name` column.
+fn csv_shows(text: &str, name: Option<&str>) -> Result<Vec<ShowMetadata>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|x| x.to_lowercase())
+        .collect::<Vec<_>>();
+    let column = |names: &[&str]| headers.iter().position(|x| names.contains(&x.as_str()));
+    let show_column = column(&["show", "series", "series_name", "show_name"]);
+    let year_column = column(&["year", "premiered", "first_aired"]);
+    let season_column = column(&["season", "season_number"]).ok_or("there is no season column")?;
+    let episode_column =
+        column(&["episode", "number", "episode_number"]).ok_or("there is no episode column")?;
+    let title_column = column(&["title", "name", "episode_name"]);
+    let mut shows: Vec<ShowMetadata> = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let line = record.position().map(|x| x.line()).unwrap_or(0);
+        let cell =
+            |column: Option<usize>| column.and_then(|x| record.get(x)).filter(|x| !x.is_empty());
+        let show = cell(show_column)
+            .or(name)
+            .ok_or(format!("line {line} has no show, give it with --show"))?;
+        let year = cell(year_column).and_then(|x| x.get(..4)?.parse().ok());
+        let (Some(season), Some(episode)) = (
+            cell(Some(season_column)).and_then(|x| x.parse().ok()),
+            cell(Some(episode_column)).and_then(|x| x.parse().ok()),
+        ) else {
+            return Err(format!(
+                "line {line} has a season or episode that is not a number"
+            ));
+        };
+        let episode = EpisodeMetadata {
+            season,
+            episode,
+            title: cell(title_column).map(|x| x.to_owned()),
+        };
+        match shows.iter_mut().find(|x| x.name == show && x.year == year) {
+            Some(show) => show.episodes.push(episode),
+            None => shows.push(ShowMetadata {
+                name: show.to_owned(),
+                year,
+                episodes: vec![episode],
+            }),
+        }
+    }
+    Ok(shows)
+}
+
+/// Parses a metadata dump exported from TVmaze, TVDB or a similar service into the shows it holds.
+///
+/// The dump is read as CSV if the file has a `csv` extension, as described in [`csv_shows()`], else as JSON, as described in [`json_shows()`].
+/// The given name is used for the dumps that do not name their show.
+///
+/// # Errors
+/// If the dump is not valid JSON or CSV, does not hold a show, or names no show when no name is given.
+///
+/// # Example
+/// ```
+/// let shows = parse_metadata(r#"{"name":"Show","premiered":"2008-01-20","_embedded":{"episodes":[{"season":1,"number":1,"name":"Pilot"}]}}"#, false, None)?;
+/// assert_eq!(shows[0].year, Some(2008));
+/// ```
+pub fn parse_metadata(
+    text: &str,
+    csv: bool,
+    name: Option<&str>,
+) -> Result<Vec<ShowMetadata>, String> {
+    match csv {
+        true => csv_shows(text, name),
+        false => json_shows(
+            &serde_json::from_str(text).map_err(|e| e.to_string())?,
+            name,
+        ),
+    }
+}
+
+/// Saves a show to the metadata database, replacing the show with the same name and year along with its episodes if it was imported before.
+async fn save_show(db: &SqlitePool, show: &ShowMetadata, source: &Path) -> anyhow::Result<()> {
+    let mut tx = db.begin().await?;
+    let existing: Option<(i64,)> =
+        sqlx::query_as("SELECT id FROM shows WHERE name = ? AND year IS ?;")
+            .bind(&show.name)
+            .bind(show.year)
+            .fetch_optional(&mut tx)
+            .await?;
+    let id = match existing {
+        Some((id,)) => {
+            sqlx::query("DELETE FROM show_episodes WHERE show_id = ?;")
+                .bind(id)
+                .execute(&mut tx)
+                .await?;
+            sqlx::query("UPDATE shows SET source = ? WHERE id = ?;")
+                .bind(source.to_string_lossy())
+                .bind(id)
+                .execute(&mut tx)
+                .await?;
+            id
+        }
+        None => sqlx::query("INSERT INTO shows (name, year, source) VALUES (?,?,?);")
+            .bind(&show.name)
+            .bind(show.year)
+            .bind(source.to_string_lossy())
+            .execute(&mut tx)
+            .await?
+            .last_insert_rowid(),
+    };
+    for episode in &show.episodes {
+        sqlx::query("INSERT OR REPLACE INTO show_episodes (show_id, season, episode, title) VALUES (?,?,?,?);")
+            .bind(id)
+            .bind(episode.season)
+            .bind(episode.episode)
+            .bind(&episode.title)
+            .execute(&mut tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Imports the given metadata dumps with [`parse_metadata()`] into the database at [`metadata_path()`], printing how many shows and episodes were imported from each one.
+///
+/// A show that was already imported, with the same name and year, is replaced.
+///
+/// # Errors
+/// If a dump cannot be read or parsed, or if the database cannot be written. The dumps before it are still imported.
+///
+/// # Example
+/// ```
+/// import_metadata(&[PathBuf::from("breaking-bad.json")], None).await?;
+/// ```
+pub async fn import_metadata(files: &[PathBuf], name: Option<&str>) -> anyhow::Result<()> {
+    let db = open_metadata().await?;
+    for file in files {
+        let text = std::fs::read_to_string(file)
+            .map_err(|e| anyhow::anyhow!("Could not read {}: {e}", file.display()))?;
+        let csv = file
+            .extension()
+            .is_some_and(|x| x.eq_ignore_ascii_case("csv"));
+        let shows = parse_metadata(&text, csv, name)
+            .map_err(|e| anyhow::anyhow!("Invalid metadata dump {}: {e}", file.display()))?;
+        for show in &shows {
+            save_show(&db, show, file).await?;
+        }
+        println!(
+            "Imported {} shows and {} episodes from {}.",
+            shows.len().to_string().green(),
+            shows
+                .iter()
+                .map(|x| x.episodes.len())
+                .sum::<usize>()
+                .to_string()
+                .green(),
+            file.display()
+        );
+    }
+    Ok(())
+}
+
+/// Turns a show name into the form used to compare names, keeping only its letters and numbers in lowercase.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|x| x.is_alphanumeric())
+        .flat_map(|x| x.to_lowercase())
+        .collect()
+}
+
+/// The shows of the metadata database, loaded once so that they can be looked up while prompting.
+pub struct Metadata {
+    db: SqlitePool,
+    /// Every show of the database.
+    pub shows: Vec<MetadataShow>,
+}
+
+impl Metadata {
+    /// Opens the database at [`metadata_path()`] and loads its shows, returning [`None`] if nothing was imported yet or it cannot be read, since the metadata is only used for suggestions.
+    ///
+    /// # Example
+    /// ```
+    /// if let Some(metadata) = Metadata::open().await {
+    ///     println!("{} shows", metadata.shows.len());
+    /// }
+    /// ```
+    pub async fn open() -> Option<Metadata> {
+        if !metadata_path().ok()?.exists() {
+            return None;
+        }
+        let db = open_metadata().await.ok()?;
+        let shows = sqlx::query_as::<_, MetadataShow>(
+            "SELECT shows.id, shows.name, shows.year, COUNT(DISTINCT show_episodes.season) AS seasons, COUNT(show_episodes.episode) AS episodes FROM shows LEFT JOIN show_episodes ON show_episodes.show_id = shows.id GROUP BY shows.id ORDER BY shows.name, shows.year;",
+        )
+        .fetch_all(&db)
+        .await
+        .ok()?;
+        Some(Metadata { db, shows })
+    }
+
+    /// Returns the shows whose name matches the given series name, ignoring case, spaces and punctuation.
+    ///
+    /// If the series name ends with a year, such as `Doctor Who (2005)`, only the show of that year matches.
+    ///
+    /// # Example
+    /// ```
+    /// let shows = metadata.find("doctor who (2005)");
+    /// ```
+    pub fn find(&self, series_name: &str) -> Vec<&MetadataShow> {
+        let with_year = Regex::new(r"^(.*?)\s*\((\d{4})\)$").unwrap();
+        let (name, year) = match with_year.captures(series_name) {
+            Some(captures) => (
+                captures.get(1).unwrap().as_str(),
+                captures[2].parse::<u32>().ok(),
+            ),
+            None => (series_name, None),
+        };
+        let name = normalize(name);
+        self.shows
+            .iter()
+            .filter(|x| normalize(&x.name) == name && (year.is_none() || x.year == year))
+            .collect()
+    }
+
+    /// Returns the only show matching the given series name with [`Metadata::find()`], or [`None`] if there is none or more than one.
+    pub fn lookup(&self, series_name: &str) -> Option<&MetadataShow> {
+        match self.find(series_name)[..] {
+            [show] => Some(show),
+            _ => None,
+        }
+    }
+
+    /// Returns every season of a show along with how many episodes it has, in order.
+    ///
+    /// # Errors
+    /// If the database cannot be read.
+    pub async fn season_episodes(&self, show: &MetadataShow) -> anyhow::Result<Vec<(u32, u32)>> {
+        Ok(sqlx::query_as(
+            "SELECT season, COUNT(*) FROM show_episodes WHERE show_id = ? GROUP BY season ORDER BY season;",
+        )
+        .bind(show.id)
+        .fetch_all(&self.db)
+        .await?)
+    }
+
+    /// Returns the titles of the episodes of a show, sanitized with [`sanitize_title()`], so that they can be used like the ones of an episode list.
+    ///
+    /// # Errors
+    /// If the database cannot be read.
+    pub async fn titles(&self, show: &MetadataShow) -> anyhow::Result<Titles> {
+        let episodes: Vec<(u32, u32, String)> = sqlx::query_as(
+            "SELECT season, episode, title FROM show_episodes WHERE show_id = ? AND title IS NOT NULL;",
+        )
+        .bind(show.id)
+        .fetch_all(&self.db)
+        .await?;
+        let mut titles = Titles::default();
+        for (season, episode, title) in episodes {
+            if let Some(title) = sanitize_title(&title) {
+                titles.entries.insert((Some(season), episode), title);
+            }
+        }
+        Ok(titles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode(season: u32, episode: u32, title: Option<&str>) -> EpisodeMetadata {
+        EpisodeMetadata {
+            season,
+            episode,
+            title: title.map(|x| x.to_owned()),
+        }
+    }
+
+    fn show(name: &str, year: Option<u32>, episodes: Vec<EpisodeMetadata>) -> ShowMetadata {
+        ShowMetadata {
+            name: name.to_owned(),
+            year,
+            episodes,
+        }
+    }
+
+    #[test]
+    fn parse_metadata_reads_tvmaze_shows() {
+        let text = r#"{
+            "name": "Breaking Bad",
+            "premiered": "2008-01-20",
+            "_embedded": {"episodes": [
+                {"season": 1, "number": 1, "name": "Pilot"},
+                {"season": 1, "number": 2, "name": " Cat's in the Bag... "},
+                {"season": 1, "number": null, "name": "Special"}
+            ]}
+        }"#;
+        assert_eq!(
+            parse_metadata(text, false, None),
+            Ok(vec![show(
+                "Breaking Bad",
+                Some(2008),
+                vec![
+                    episode(1, 1, Some("Pilot")),
+                    episode(1, 2, Some("Cat's in the Bag...")),
+                ]
+            )])
+        );
+        assert_eq!(
+            parse_metadata(&format!("[{text}, {{\"name\": \"Other\"}}]"), false, None)
+                .map(|x| x.iter().map(|x| x.name.clone()).collect::<Vec<_>>()),
+            Ok(vec!["Breaking Bad".to_owned(), "Other".to_owned()])
+        );
+    }
+
+    #[test]
+    fn parse_metadata_reads_tvdb_responses() {
+        let text = r#"{"data": {
+            "series": {"seriesName": "Doctor Who", "firstAired": "2005-03-26"},
+            "episodes": [
+                {"airedSeason": "1", "airedEpisodeNumber": "1", "episodeName": "Rose"},
+                {"seasonNumber": 0, "number": 1, "name": ""}
+            ]
+        }}"#;
+        assert_eq!(
+            parse_metadata(text, false, None),
+            Ok(vec![show(
+                "Doctor Who",
+                Some(2005),
+                vec![episode(1, 1, Some("Rose")), episode(0, 1, None)]
+            )])
+        );
+        let text = r#"{"data": {"name": "Lost", "year": "2004", "episodes": [
+            {"seasonNumber": 1, "number": 1, "name": "Pilot (1)"}
+        ]}}"#;
+        assert_eq!(
+            parse_metadata(text, false, None),
+            Ok(vec![show(
+                "Lost",
+                Some(2004),
+                vec![episode(1, 1, Some("Pilot (1)"))]
+            )])
+        );
+    }
+
+    #[test]
+    fn parse_metadata_needs_a_name_for_episode_lists() {
+        let text = r#"[{"season": 2, "number": 3, "name": "Three"}]"#;
+        assert_eq!(
+            parse_metadata(text, false, None),
+            Err("the file only holds episodes, give the name of the show with --show".to_owned())
+        );
+        assert_eq!(
+            parse_metadata(text, false, Some("Show")),
+            Ok(vec![show("Show", None, vec![episode(2, 3, Some("Three"))])])
+        );
+        assert_eq!(
+            parse_metadata(r#"{"_embedded": {"episodes": []}}"#, false, None),
+            Err("a show has no name, give it with --show".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_metadata_rejects_invalid_json() {
+        assert_eq!(
+            parse_metadata("42", false, None),
+            Err("the file does not hold a show or a list of episodes".to_owned())
+        );
+        assert!(parse_metadata("{", false, None).is_err());
+    }
+
+    #[test]
+    fn parse_metadata_reads_csv_columns() {
+        let text = "Series_Name,First_Aired,Season_Number,Number,Title\n\
+            Doctor Who,2005-03-26,1,1,Rose\n\
+            Doctor Who,2005-03-26,1,2,\"The End of the World\"\n\
+            Doctor Who,1963,1,1,An Unearthly Child\n\
+            Lost,,1,1,\n";
+        assert_eq!(
+            parse_metadata(text, true, None),
+            Ok(vec![
+                show(
+                    "Doctor Who",
+                    Some(2005),
+                    vec![
+                        episode(1, 1, Some("Rose")),
+                        episode(1, 2, Some("The End of the World")),
+                    ]
+                ),
+                show(
+                    "Doctor Who",
+                    Some(1963),
+                    vec![episode(1, 1, Some("An Unearthly Child"))]
+                ),
+                show("Lost", None, vec![episode(1, 1, None)]),
+            ])
+        );
+        assert_eq!(
+            parse_metadata("season,episode\n1,1\n", true, Some("Show")),
+            Ok(vec![show("Show", None, vec![episode(1, 1, None)])])
+        );
+    }
+
+    #[test]
+    fn parse_metadata_rejects_invalid_csv() {
+        assert_eq!(
+            parse_metadata("show,episode\nShow,1\n", true, None),
+            Err("there is no season column".to_owned())
+        );
+        assert_eq!(
+            parse_metadata("show,season,title\nShow,1,Pilot\n", true, None),
+            Err("there is no episode column".to_owned())
+        );
+        assert_eq!(
+            parse_metadata("season,episode\n1,1\n", true, None),
+            Err("line 2 has no show, give it with --show".to_owned())
+        );
+        assert_eq!(
+            parse_metadata("show,season,episode\nShow,1,1\nShow,one,2\n", true, None),
+            Err("line 3 has a season or episode that is not a number".to_owned())
+        );
+    }
+
+    #[test]
+    fn normalize_keeps_letters_and_numbers() {
+        assert_eq!(
+            normalize("Marvel's Agents of S.H.I.E.L.D."),
+            "marvelsagentsofshield"
+        );
+        assert_eq!(normalize("Doctor Who (2005)"), "doctorwho2005");
+        assert_eq!(normalize("Élite"), "élite");
+        assert_eq!(normalize(" - "), "");
+    }
+}
@@ -0,0 +1,316 @@
+use crate::mapping::{parse_mapping, Mapping, Parsed};
+use crate::validate::Selection;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use std::path::Path;
+
+/// State of the full screen file picker shown by [`pick_files()`].
+///
+/// Holds the file names of the directory, the current fuzzy filter, which of the files match the filter, the position of the cursor and the files that have been checked in the order they were checked.
+/// When the filter starts with [`crate::mapping::MAPPING_PREFIX`], it is a [`Mapping`] instead, and what each file was parsed to is kept as well.
+/// Also used by the file pane of [`crate::tui::run_tui()`].
+pub(crate) struct Picker {
+    pub(crate) names: Vec<String>,
+    pub(crate) filter: String,
+    visible: Vec<usize>,
+    pub(crate) checked: Vec<usize>,
+    list: ListState,
+    mapping: Option<Result<Mapping, String>>,
+    parsed: Vec<Result<Parsed, String>>,
+}
+
+impl Picker {
+    pub(crate) fn new(names: Vec<String>) -> Self {
+        let mut picker = Picker {
+            names,
+            filter: String::new(),
+            visible: Vec::new(),
+            checked: Vec::new(),
+            list: ListState::default(),
+            mapping: None,
+            parsed: Vec::new(),
+        };
+        picker.refilter();
+        picker
+    }
+
+    /// Recomputes which files match the filter, keeping them in the natural order of the directory.
+    ///
+    /// When the filter is a valid [`Mapping`], the files are parsed with [`Mapping::map_files()`] and only the ones it parsed are shown. While it is not valid yet, every file is shown.
+    pub(crate) fn refilter(&mut self) {
+        let matcher = SkimMatcherV2::default();
+        self.mapping = parse_mapping(&self.filter);
+        self.parsed = match &self.mapping {
+            Some(Ok(mapping)) => mapping.map_files(&self.names),
+            _ => Vec::new(),
+        };
+        self.visible = (0..self.names.len())
+            .filter(|index| match &self.mapping {
+                Some(Ok(_)) => self.parsed[*index].is_ok(),
+                Some(Err(_)) => true,
+                None => {
+                    self.filter.is_empty()
+                        || matcher
+                            .fuzzy_match(&self.names[*index], &self.filter)
+                            .is_some()
+                }
+            })
+            .collect();
+        self.list.select(match self.visible.is_empty() {
+            true => None,
+            false => Some(
+                self.list
+                    .selected()
+                    .unwrap_or(0)
+                    .min(self.visible.len() - 1),
+            ),
+        });
+    }
+
+    /// Returns the last line of the error of the filter if it is an invalid [`Mapping`], which is short enough to fit the title of the filter.
+    pub(crate) fn mapping_error(&self) -> Option<&str> {
+        match &self.mapping {
+            Some(Err(e)) => e.lines().last(),
+            _ => None,
+        }
+    }
+
+    /// Returns the checked files as a [`Selection`].
+    ///
+    /// When the filter is a valid [`Mapping`], only the checked files it parsed are chosen, or every file it parsed if none of them is checked, and [`Selection::parsed`] holds what each of them was parsed to.
+    pub(crate) fn selection(&self) -> Selection {
+        let indexes = match &self.mapping {
+            Some(Ok(_)) => {
+                let checked = self
+                    .checked
+                    .iter()
+                    .copied()
+                    .filter(|x| self.visible.contains(x))
+                    .collect::<Vec<_>>();
+                match checked.is_empty() {
+                    true => self.visible.clone(),
+                    false => checked,
+                }
+            }
+            _ => self.checked.clone(),
+        };
+        Selection {
+            parsed: indexes
+                .iter()
+                .filter_map(|x| self.parsed.get(*x).cloned()?.ok())
+                .collect(),
+            indexes,
+            ignored: Vec::new(),
+        }
+    }
+
+    fn current(&self) -> Option<usize> {
+        self.list.selected().map(|row| self.visible[row])
+    }
+
+    pub(crate) fn toggle(&mut self) {
+        if let Some(index) = self.current() {
+            match self.checked.iter().position(|x| *x == index) {
+                Some(position) => {
+                    self.checked.remove(position);
+                }
+                None => self.checked.push(index),
+            }
+        }
+    }
+
+    pub(crate) fn check(&mut self) {
+        if let Some(index) = self.current() {
+            if !self.checked.contains(&index) {
+                self.checked.push(index);
+            }
+        }
+    }
+
+    pub(crate) fn move_cursor(&mut self, down: bool) {
+        if let Some(row) = self.list.selected() {
+            let row = match down {
+                true => (row + 1).min(self.visible.len() - 1),
+                false => row.saturating_sub(1),
+            };
+            self.list.select(Some(row));
+        }
+    }
+
+    pub(crate) fn toggle_all(&mut self) {
+        match self
+            .visible
+            .iter()
+            .all(|index| self.checked.contains(index))
+        {
+            true => self.checked.retain(|index| !self.visible.contains(index)),
+            false => {
+                for index in self.visible.clone() {
+                    if !self.checked.contains(&index) {
+                        self.checked.push(index);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles a key press that moves the cursor, checks files or edits the filter. Returns false if the key is not one of them.
+    pub(crate) fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.toggle_all()
+            }
+            KeyCode::Char(' ') => self.toggle(),
+            KeyCode::Up | KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                self.check();
+                self.move_cursor(key.code == KeyCode::Down);
+                self.check();
+            }
+            KeyCode::Up => self.move_cursor(false),
+            KeyCode::Down => self.move_cursor(true),
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.refilter();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.filter.push(c);
+                self.refilter();
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Draws the checkbox list of the files matching the filter in the given area, with the amount of checked files in the title.
+    pub(crate) fn render_list(
+        &mut self,
+        frame: &mut ratatui::Frame,
+        area: Rect,
+        title: &str,
+        border: Color,
+    ) {
+        let items = self
+            .visible
+            .iter()
+            .map(|index| {
+                let mark = match self.checked.contains(index) {
+                    true => "[x]".green(),
+                    false => "[ ]".into(),
+                };
+                let parsed = match self.parsed.get(*index) {
+                    Some(Ok(parsed)) => format!(" → {parsed}").cyan(),
+                    _ => "".into(),
+                };
+                ListItem::new(Line::from(vec![
+                    mark,
+                    format!(" {index}. ").yellow(),
+                    self.names[*index].clone().into(),
+                    parsed,
+                ]))
+            })
+            .collect::<Vec<_>>();
+        let title = format!(
+            " {} ({} of {} selected) ",
+            title,
+            self.selection().indexes.len(),
+            self.names.len()
+        );
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(title)
+                        .border_style(Style::default().fg(border)),
+                )
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            area,
+            &mut self.list,
+        );
+    }
+
+    fn draw(&mut self, frame: &mut ratatui::Frame, path: &Path) {
+        let [filter_area, list_area, help_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let (title, color) = match self.mapping_error() {
+            Some(e) => (format!(" Filter: {e} "), Color::Red),
+            None => (" Filter ".to_owned(), Color::Green),
+        };
+        frame.render_widget(
+            Paragraph::new(self.filter.as_str()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .border_style(Style::default().fg(color)),
+            ),
+            filter_area,
+        );
+        self.render_list(frame, list_area, path.to_str().unwrap(), Color::Green);
+        frame.render_widget(
+            Paragraph::new(
+                "Type to filter, or re: and a regex | Space: toggle | Shift+Up/Down: select range | Ctrl+A: toggle all | Enter: confirm | Esc: type a range instead",
+            )
+            .dark_gray(),
+            help_area,
+        );
+    }
+}
+
+/// Shows a full screen checkbox list of the files in a directory and returns the files chosen by the user as a [`Selection`].
+///
+/// This is an alternative to typing a range for [`crate::validate::parse_range()`]. The list can be narrowed down by typing a fuzzy filter, or by typing a [`Mapping`] starting with [`crate::mapping::MAPPING_PREFIX`], in which case what each file was parsed to is shown next to it as it is typed. The keys are as follows:
+/// - Space: Check or uncheck the file under the cursor.
+/// - Shift+Up/Shift+Down: Check the file under the cursor and the file the cursor moves to, selecting a range while held.
+/// - Ctrl+A: Check all the files matching the filter, or uncheck them if they are all checked.
+/// - Enter: Confirm the selection.
+/// - Esc: Leave the picker without a selection.
+///
+/// The returned indexes are the positions of the files in `names`, in the order they were checked, so they can be used in the same way as the indexes returned by [`crate::validate::parse_range()`].
+/// If Enter is pressed with no file checked, the file under the cursor is chosen, or every parsed file when the filter is a mapping, as returned by [`Picker::selection()`]. Returns [`None`] if the user leaves the picker with Esc.
+///
+/// # Panics
+/// If the path cannot be unwrapped to a [`&str`].
+///
+/// # Example
+/// ```
+/// let files = get_files(path.to_owned(), &args.extensions)?;
+/// let selection = pick_files(&path, get_file_names(&files)?)?;
+/// ```
+pub fn pick_files(path: &Path, names: Vec<String>) -> anyhow::Result<Option<Selection>> {
+    let mut terminal = ratatui::try_init()?;
+    let mut picker = Picker::new(names);
+    let result = loop {
+        if let Err(e) = terminal.draw(|frame| picker.draw(frame, path)) {
+            break Err(e);
+        }
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e),
+        };
+        match key.code {
+            KeyCode::Esc => break Ok(None),
+            KeyCode::Enter => {
+                if picker.selection().indexes.is_empty() {
+                    picker.check();
+                }
+                break Ok(Some(picker.selection()));
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break Ok(None),
+            _ => {
+                picker.handle_key(key);
+            }
+        }
+    };
+    ratatui::try_restore()?;
+    Ok(result?)
+}
@@ -0,0 +1,339 @@
+use crate::database::*;
+use crate::files::TransferMode;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// The contents of a JSON plan file.
+///
+/// Holds every episode entry of the database along with the [`TransferMode`] chosen for them, so that the changes can be reviewed, edited by hand and executed later with the --plan-in flag.
+///
+/// # Example
+/// ```json
+/// {
+///   "transfer_mode": "move",
+///   "episodes": [
+///     {
+///       "series_name": "Show",
+///       "season": 1,
+///       "episode": 1,
+///       "title": null,
+///       "old_path": "/home/user/show1.mkv",
+///       "new_path": "/home/user/output/Show/Season 1/Show S1E1.mkv"
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Plan {
+    /// How the files are transferred to their new path.
+    pub transfer_mode: TransferMode,
+    /// The episodes to transfer.
+    pub episodes: Vec<Episode>,
+}
+
+/// A row of a CSV plan file.
+///
+/// Holds the same fields as [`Episode`] with the [`TransferMode`] as an extra column, since a CSV file has no room for a value shared by every row.
+/// Every row of a CSV plan file must have the same transfer mode.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlanRow {
+    /// Holds the series name of the episode.
+    pub series_name: String,
+    /// Holds the season number of the episode.
+    pub season: u32,
+    /// Holds the episode number of the episode.
+    pub episode: u32,
+    /// Holds the title of the episode, empty if it has none.
+    pub title: Option<String>,
+    /// Holds the current path of the file of the episode.
+    pub old_path: String,
+    /// Holds the output path of the file of the episode.
+    pub new_path: String,
+    /// How the file is transferred to its new path.
+    pub transfer_mode: TransferMode,
+}
+
+/// Returns true if the given plan file should be read and written as CSV, which is the case when its extension is `csv`. Every other file is treated as JSON.
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .map(|x| x.eq_ignore_ascii_case("csv"))
+        .unwrap_or(false)
+}
+
+/// Saves every episode entry of the database along with the given [`TransferMode`] to a plan file with [`write_plan()`].
+///
+/// # Example
+/// ```
+/// save_plan(&db, Path::new("plan.json"), TransferMode::Move).await?;
+/// ```
+pub async fn save_plan(
+    db: &sqlx::SqlitePool,
+    path: &Path,
+    transfer_mode: TransferMode,
+) -> anyhow::Result<()> {
+    let plan = Plan {
+        transfer_mode,
+        episodes: select_plan(db).await?,
+    };
+    write_plan(path, &plan)
+}
+
+/// Writes a plan to a plan file.
+///
+/// If the file has a `.csv` extension it is written as CSV with a [`PlanRow`] per episode, else it is written as pretty printed JSON following [`Plan`]. An existing file is overwritten.
+///
+/// # Example
+/// ```
+/// write_plan(Path::new("plan.csv"), &plan)?;
+/// ```
+pub fn write_plan(path: &Path, plan: &Plan) -> anyhow::Result<()> {
+    if is_csv(path) {
+        let mut writer = csv::Writer::from_path(path)?;
+        for episode in &plan.episodes {
+            writer.serialize(PlanRow {
+                series_name: episode.series_name.clone(),
+                season: episode.season,
+                episode: episode.episode,
+                title: episode.title.clone(),
+                old_path: episode.old_path.clone(),
+                new_path: episode.new_path.clone(),
+                transfer_mode: plan.transfer_mode,
+            })?;
+        }
+        writer.flush()?;
+    } else {
+        std::fs::write(path, serde_json::to_string_pretty(plan)? + "\n")?;
+    }
+    Ok(())
+}
+
+/// Reads a plan file written by [`save_plan()`], or written by hand in the same format.
+///
+/// If the file has a `.csv` extension it is read as CSV, else it is read as JSON. An empty CSV plan uses [`TransferMode::Move`].
+///
+/// # Errors
+/// If the file cannot be read or parsed, or if the rows of a CSV plan have different transfer modes.
+///
+/// # Example
+/// ```
+/// let plan = read_plan(Path::new("plan.csv"))?;
+/// ```
+pub fn read_plan(path: &Path) -> anyhow::Result<Plan> {
+    if !is_csv(path) {
+        return Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?);
+    }
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut transfer_mode = None;
+    let mut episodes = Vec::new();
+    for row in reader.deserialize::<PlanRow>() {
+        let row = row?;
+        if transfer_mode.is_some_and(|x| x != row.transfer_mode) {
+            anyhow::bail!("Every row of a CSV plan must have the same transfer_mode.");
+        }
+        transfer_mode = Some(row.transfer_mode);
+        episodes.push(Episode {
+            series_name: row.series_name,
+            season: row.season,
+            episode: row.episode,
+            title: row.title,
+            old_path: row.old_path,
+            new_path: row.new_path,
+        });
+    }
+    Ok(Plan {
+        transfer_mode: transfer_mode.unwrap_or_default(),
+        episodes,
+    })
+}
+
+/// Reads a plan file with [`read_plan()`] and inserts its episodes into the database, returning the [`TransferMode`] of the plan.
+///
+/// # Errors
+/// If the plan cannot be read, or if two episodes of the plan have the same old_path or new_path since those columns are unique.
+///
+/// # Example
+/// ```
+/// let transfer_mode = load_plan(&db, Path::new("plan.json")).await?;
+/// move_files(&db, &args).await?;
+/// ```
+pub async fn load_plan(db: &sqlx::SqlitePool, path: &Path) -> anyhow::Result<TransferMode> {
+    let plan = read_plan(path)?;
+    for episode in plan.episodes {
+        insert_episode(
+            db,
+            &episode.series_name,
+            episode.season,
+            episode.episode,
+            episode.title.as_deref(),
+            PathBuf::from(episode.old_path),
+            PathBuf::from(episode.new_path),
+        )
+        .await?;
+    }
+    Ok(plan.transfer_mode)
+}
+
+/// Returns the path of the plan file holding the progress of the last run, `rplexfmtr/resume.json` inside of the data directory of the user.
+///
+/// # Errors
+/// If the data directory of the user cannot be found.
+pub fn progress_path() -> anyhow::Result<PathBuf> {
+    match dirs::data_dir() {
+        Some(dir) => Ok(dir.join("rplexfmtr").join("resume.json")),
+        None => anyhow::bail!("Could not find the data directory to keep the progress of the run."),
+    }
+}
+
+/// Returns an error if the plan file at [`progress_path()`] exists, which means the last run stopped before renaming every file.
+///
+/// Used before starting a run that is not a resume, so that the files left over by the last run are not forgotten.
+///
+/// # Errors
+/// If the plan file of the last run exists.
+pub fn check_progress() -> anyhow::Result<()> {
+    match progress_path() {
+        Ok(path) if path.exists() => anyhow::bail!(
+            "The last run stopped before renaming every file, the files it left over are kept in {}. Run `rplexfmtr resume` to rename them, or remove the file to start a new run.",
+            path.display()
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Removes the plan file at [`progress_path()`], if any.
+///
+/// Used by `rplexfmtr watch`, which tries the files left in place again by itself once they change.
+///
+/// # Errors
+/// If the plan file exists but cannot be removed.
+pub fn discard_progress() -> anyhow::Result<()> {
+    match progress_path().map(std::fs::remove_file) {
+        Ok(Err(e)) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the given episodes with their paths made absolute with [`Episode::absolute()`].
+fn absolute_episodes(episodes: &[Episode]) -> anyhow::Result<Vec<Episode>> {
+    episodes.iter().map(|x| x.absolute()).collect()
+}
+
+/// Progress of a run of [`crate::files::move_files()`], kept up to date in the plan file at [`progress_path()`] so that an interrupted run can be completed with `rplexfmtr resume`.
+///
+/// The plan file holds the episodes that are still left to transfer. It is removed once every episode has been transferred.
+/// If the data directory of the user cannot be found, a warning is printed and the progress is only kept in memory.
+///
+/// # Example
+/// ```
+/// let mut progress = Progress::new(&episodes, TransferMode::Move, false)?;
+/// for episode in &episodes {
+///     transfer_file(Path::new(&episode.old_path), Path::new(&episode.new_path), TransferMode::Move)?;
+///     progress.done(episode)?;
+/// }
+/// progress.finish()?;
+/// ```
+pub struct Progress {
+    path: Option<PathBuf>,
+    plan: Plan,
+    resuming: bool,
+}
+
+impl Progress {
+    /// Saves every given episode as left to transfer with the given [`TransferMode`].
+    /// Their paths are made absolute with [`Episode::absolute()`], so that `rplexfmtr resume` can be run from any directory.
+    ///
+    /// The progress of the last run is only replaced when resuming it, else [`check_progress()`] must pass.
+    ///
+    /// # Errors
+    /// If the last run left files over and this run does not resume it, or if the plan file cannot be written.
+    pub fn new(
+        episodes: &[Episode],
+        transfer_mode: TransferMode,
+        resuming: bool,
+    ) -> anyhow::Result<Progress> {
+        if !resuming {
+            check_progress()?;
+        }
+        let path = match progress_path() {
+            Ok(path) => Some(path),
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "{e} The run cannot be resumed if it stops before every file is renamed."
+                    )
+                    .yellow()
+                );
+                None
+            }
+        };
+        if let Some(parent) = path.as_ref().and_then(|x| x.parent()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let progress = Progress {
+            path,
+            plan: Plan {
+                transfer_mode,
+                episodes: absolute_episodes(episodes)?,
+            },
+            resuming,
+        };
+        progress.save()?;
+        Ok(progress)
+    }
+
+    /// Writes the episodes left to transfer to the plan file, if the data directory was found.
+    fn save(&self) -> anyhow::Result<()> {
+        match &self.path {
+            Some(path) => write_plan(path, &self.plan),
+            None => Ok(()),
+        }
+    }
+
+    /// Marks the given episode as transferred, removing it from the plan file.
+    ///
+    /// # Errors
+    /// If the plan file cannot be written.
+    pub fn done(&mut self, episode: &Episode) -> anyhow::Result<()> {
+        let episode = episode.absolute()?;
+        self.plan
+            .episodes
+            .retain(|x| x.old_path != episode.old_path);
+        self.save()
+    }
+
+    /// Resets the progress once the transfers of the run are rolled back, given every episode of the run.
+    ///
+    /// When resuming, the episodes are saved as left to transfer again, since they are still the files the last run left over. Otherwise nothing is left over and the plan file is removed, so that it does not block the next run.
+    ///
+    /// # Errors
+    /// If the plan file cannot be written or removed.
+    pub fn restore(&mut self, episodes: &[Episode]) -> anyhow::Result<()> {
+        match self.resuming {
+            true => self.plan.episodes = absolute_episodes(episodes)?,
+            false => self.plan.episodes.clear(),
+        }
+        match self.plan.episodes.is_empty() {
+            true => discard_progress(),
+            false => self.save(),
+        }
+    }
+
+    /// The amount of episodes that are still left to transfer.
+    pub fn remaining(&self) -> usize {
+        self.plan.episodes.len()
+    }
+
+    /// Removes the plan file if every episode has been transferred, else keeps it so that the remaining episodes can be transferred with `rplexfmtr resume`.
+    ///
+    /// # Errors
+    /// If the plan file cannot be removed.
+    pub fn finish(self) -> anyhow::Result<()> {
+        if let (Some(path), true) = (&self.path, self.plan.episodes.is_empty()) {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use crate::database::Episode;
+use crate::files::{strategy, Strategy, TransferMode};
+use colored::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// A problem found by [`preflight()`] that would make [`crate::files::move_files()`] fail.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Problem {
+    /// The file or directory the problem was found in.
+    pub path: PathBuf,
+    /// Why the file or directory would make the transfer fail.
+    pub reason: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.reason)
+    }
+}
+
+/// The result of the checks made by [`preflight()`] before the files are transferred.
+#[derive(Clone, Debug, Default)]
+pub struct Preflight {
+    /// Total size in bytes of the files that have to be copied, either with [`TransferMode::Copy`] or to another file system with [`TransferMode::Move`].
+    pub copy_bytes: u64,
+    /// Every problem found, empty if the files can be transferred.
+    pub problems: Vec<Problem>,
+}
+
+impl Preflight {
+    /// Returns the amount of data that will be copied followed by every problem found, one per line, or an empty string if there is nothing to report.
+    ///
+    /// # Example
+    /// ```
+    /// print!("{}", preflight(&episodes, TransferMode::Move).report());
+    /// ```
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        if self.copy_bytes > 0 {
+            report += &format!(
+                "{} will be copied.\n",
+                format_bytes(self.copy_bytes).yellow()
+            );
+        }
+        if !self.problems.is_empty() {
+            report += &format!(
+                "The following {} problems were found:\n",
+                self.problems.len().to_string().red()
+            );
+            for problem in &self.problems {
+                report += &format!("  {}\n", problem.to_string().red());
+            }
+        }
+        report
+    }
+}
+
+/// Formats an amount of bytes with the largest binary unit that keeps it above 1, such as `1.5 GiB`.
+///
+/// # Example
+/// ```
+/// assert_eq!(format_bytes(1536), "1.5 KiB");
+/// ```
+pub fn format_bytes(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{size:.1} {}", units[unit]),
+    }
+}
+
+/// Returns the closest ancestor of the given path that exists, including the path itself, which is the directory a missing folder would be created in.
+pub fn existing_ancestor(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|x| x.exists())
+}
+
+/// Returns the ID of the device, and so of the file system, the given path is in.
+#[cfg(unix)]
+pub fn device(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|x| x.dev())
+}
+
+/// Returns the ID of the device, and so of the file system, the given path is in.
+///
+/// Device IDs are not available on this platform, so every path is treated as being in the same file system.
+#[cfg(not(unix))]
+pub fn device(path: &Path) -> Option<u64> {
+    std::fs::metadata(path).ok().map(|_| 0)
+}
+
+/// Returns the amount of bytes that can be written by the user to the file system the given path is in.
+///
+/// The conversions are only needed on the platforms where [`nix::sys::statvfs::Statvfs`] does not use [`u64`].
+#[cfg(unix)]
+#[allow(clippy::useless_conversion)]
+fn free_space(path: &Path) -> Option<u64> {
+    nix::sys::statvfs::statvfs(path)
+        .ok()
+        .map(|x| u64::from(x.blocks_available()) * u64::from(x.fragment_size()))
+}
+
+/// Returns the amount of bytes that can be written by the user to the file system the given path is in.
+///
+/// Free space is not checked on this platform.
+#[cfg(not(unix))]
+fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Returns true if the user can create and delete files in the given directory.
+#[cfg(unix)]
+fn writable(dir: &Path) -> bool {
+    use nix::unistd::{access, AccessFlags};
+    access(dir, AccessFlags::W_OK | AccessFlags::X_OK).is_ok()
+}
+
+/// Returns true if the user can create and delete files in the given directory.
+#[cfg(not(unix))]
+fn writable(dir: &Path) -> bool {
+    std::fs::metadata(dir)
+        .map(|x| !x.permissions().readonly())
+        .unwrap_or(false)
+}
+
+/// Checks that the given episodes can be transferred with the given [`TransferMode`] before any file is touched, so that problems can be shown in the preview instead of making [`crate::files::move_files()`] fail halfway through.
+///
+/// The following is checked:
+/// - The file at the old_path of every episode exists.
+/// - The user can write to the directory every new_path will be created in, which is the output path unless the series or season folders already exist.
+/// - With [`TransferMode::Move`], the user can delete files from the directory of every old_path.
+/// - The file systems of the new paths have enough free space for the files that have to be copied to them, which are the files whose [`Strategy`] is [`Strategy::Copy`] or [`Strategy::CopyDelete`].
+///
+/// # Example
+/// ```
+/// let preflight = preflight(&select_plan(&db).await?, args.transfer_mode());
+/// if !preflight.problems.is_empty() {
+///     print!("{}", preflight.report());
+/// }
+/// ```
+pub fn preflight(episodes: &[Episode], mode: TransferMode) -> Preflight {
+    let mut preflight = Preflight::default();
+    let mut target_dirs = HashSet::new();
+    let mut source_dirs = HashSet::new();
+    let mut needed: HashMap<u64, (PathBuf, u64)> = HashMap::new();
+    for episode in episodes {
+        let old_path = Path::new(&episode.old_path);
+        let Ok(metadata) = std::fs::metadata(old_path) else {
+            preflight.problems.push(Problem {
+                path: old_path.to_path_buf(),
+                reason: "file does not exist or cannot be read".to_owned(),
+            });
+            continue;
+        };
+        let Some(target_dir) = Path::new(&episode.new_path)
+            .parent()
+            .and_then(existing_ancestor)
+        else {
+            continue;
+        };
+        if target_dirs.insert(target_dir.to_path_buf()) && !writable(target_dir) {
+            preflight.problems.push(Problem {
+                path: target_dir.to_path_buf(),
+                reason: "no permission to create files".to_owned(),
+            });
+        }
+        if mode == TransferMode::Move {
+            if let Some(source_dir) = old_path.parent().and_then(existing_ancestor) {
+                if source_dirs.insert(source_dir.to_path_buf()) && !writable(source_dir) {
+                    preflight.problems.push(Problem {
+                        path: source_dir.to_path_buf(),
+                        reason: "no permission to delete files".to_owned(),
+                    });
+                }
+            }
+        }
+        let copied = matches!(
+            strategy(old_path, Path::new(&episode.new_path), mode),
+            Strategy::Copy | Strategy::CopyDelete
+        );
+        if copied {
+            preflight.copy_bytes += metadata.len();
+            if let Some(device) = device(target_dir) {
+                needed
+                    .entry(device)
+                    .or_insert((target_dir.to_path_buf(), 0))
+                    .1 += metadata.len();
+            }
+        }
+    }
+    let mut needed = needed.into_values().collect::<Vec<_>>();
+    needed.sort();
+    for (dir, bytes) in needed {
+        if let Some(free) = free_space(&dir) {
+            if free < bytes {
+                preflight.problems.push(Problem {
+                    path: dir,
+                    reason: format!(
+                        "{} needed but only {} free",
+                        format_bytes(bytes),
+                        format_bytes(free)
+                    ),
+                });
+            }
+        }
+    }
+    preflight
+}
@@ -0,0 +1,897 @@
+use crate::database::*;
+use crate::files::*;
+use crate::library::*;
+use crate::mapping::{mapping_preview, parse_mapping, Parsed};
+use crate::metadata::Metadata;
+use crate::picker::pick_files;
+use crate::rules::match_directory;
+use crate::titles::{load_titles, parse_titles, Titles};
+use crate::validate::*;
+use colored::*;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Config, Context, DefaultEditor, Editor, Helper};
+use std::fs::DirEntry;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+/// Maximum amount of series names kept in the history file.
+const HISTORY_SIZE: usize = 1000;
+
+/// [`rustyline`] helper that completes series names with Tab.
+///
+/// The candidates are the series names given when creating the [`Prompter`], usually the folders already present in the libraries as returned by [`crate::files::get_show_names()`].
+/// Matching is case insensitive and always completes the whole line.
+pub struct ShowNames {
+    /// Series names offered as completions.
+    pub names: Vec<String>,
+}
+
+impl Completer for ShowNames {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let typed = line[..pos].to_lowercase();
+        Ok((
+            0,
+            self.names
+                .iter()
+                .filter(|name| name.to_lowercase().starts_with(&typed))
+                .cloned()
+                .collect(),
+        ))
+    }
+}
+
+impl Hinter for ShowNames {
+    type Hint = String;
+}
+
+impl Highlighter for ShowNames {}
+
+impl Validator for ShowNames {}
+
+impl Helper for ShowNames {}
+
+/// Reads the answers to the prompts with line editing, so that the arrow keys, Home, End and the usual readline shortcuts work.
+///
+/// Series names are read with [`Prompter::series_name()`], which completes names with Tab using [`ShowNames`] and keeps a history of the names given in previous runs in `history.txt` inside the rplexfmtr data directory (`~/.local/share/rplexfmtr` on Linux).
+/// Every other answer is read with [`Prompter::answer()`], which only keeps a history for the current run.
+///
+/// If the user presses Ctrl+C the process exits with a status code of `130`, and if they press Ctrl+D it exits with a status code of `0`.
+pub struct Prompter {
+    names: Editor<ShowNames, DefaultHistory>,
+    answers: DefaultEditor,
+    history_path: Option<PathBuf>,
+}
+
+impl Prompter {
+    /// Creates the line editors for the prompts, offering the given series names as completions and loading the history of series names from previous runs.
+    ///
+    /// A missing or unreadable history file is not an error, the history simply starts empty.
+    ///
+    /// # Example
+    /// ```
+    /// let mut prompter = Prompter::new(get_show_names(&args.libraries[0].path)?)?;
+    /// ```
+    pub fn new(names: Vec<String>) -> anyhow::Result<Self> {
+        let config = Config::builder()
+            .auto_add_history(false)
+            .history_ignore_dups(true)?
+            .max_history_size(HISTORY_SIZE)?
+            .completion_type(CompletionType::List)
+            .build();
+        let mut editor = Editor::with_config(config)?;
+        editor.set_helper(Some(ShowNames { names }));
+        let history_path = dirs::data_dir().map(|x| x.join("rplexfmtr").join("history.txt"));
+        if let Some(path) = &history_path {
+            let _ = editor.load_history(path);
+        }
+        Ok(Prompter {
+            names: editor,
+            answers: DefaultEditor::with_config(Config::builder().auto_add_history(true).build())?,
+            history_path,
+        })
+    }
+
+    /// Reads a series name, completing it with Tab and saving it to the history file if it is accepted by [`valid_name()`].
+    ///
+    /// The trailing whitespace of the name is removed. Failing to save the history file is not an error.
+    ///
+    /// # Example
+    /// ```
+    /// println!("What would you like the entries for {} to be titled?: ", path);
+    /// let name = prompter.series_name()?;
+    /// ```
+    pub fn series_name(&mut self) -> anyhow::Result<String> {
+        let name = read(self.names.readline(""))?;
+        if valid_name(&name) {
+            self.names.add_history_entry(name.as_str())?;
+            if let Some(path) = &self.history_path {
+                if let Some(parent) = path.parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+                let _ = self.names.save_history(path);
+            }
+        }
+        Ok(name)
+    }
+
+    /// Reads the answer to any prompt that is not a series name, with the trailing whitespace removed.
+    ///
+    /// # Example
+    /// ```
+    /// println!("What season do these files belong to?");
+    /// let season = prompter.answer()?;
+    /// ```
+    pub fn answer(&mut self) -> anyhow::Result<String> {
+        read(self.answers.readline(""))
+    }
+
+    /// Lets the user edit individual rows of the preview table before executing the changes, showing the table again after every edit.
+    ///
+    /// Every answer is parsed with [`parse_edit()`] and applied with [`crate::files::apply_edit()`]. Invalid edits and edits that cannot be applied are reported and the user is asked again.
+    /// The edit step ends when the user presses enter without typing an edit, returning [`None`], or when a [`Navigation`] command is typed, returning the command.
+    ///
+    /// # Example
+    /// ```
+    /// let navigation = prompter.edit_plan(&db, &args).await?;
+    /// ```
+    pub async fn edit_plan(
+        &mut self,
+        db: &sqlx::SqlitePool,
+        args: &Cli,
+    ) -> anyhow::Result<Option<Navigation>> {
+        crate::files::preview_changes(db, args).await?;
+        loop {
+            println!(
+                "Type {}, {}, {} or {}, or press enter when you are done:",
+                "episode <row> <number>".yellow(),
+                "season <row> <number>".yellow(),
+                "exclude <row>".yellow(),
+                "name <row> <file name>".yellow()
+            );
+            let ans = self.answer()?;
+            if ans.is_empty() {
+                return Ok(None);
+            }
+            if let Some(navigation) = parse_navigation(&ans) {
+                return Ok(Some(navigation));
+            }
+            match parse_edit(&ans) {
+                Some(edit) => match crate::files::apply_edit(db, args, edit).await {
+                    Ok(_) => crate::files::preview_changes(db, args).await?,
+                    Err(e) => println!("{}", e.to_string().red()),
+                },
+                None => println!("{} is not a valid edit.", ans.red()),
+            }
+        }
+    }
+}
+
+/// Turns the result of reading a line into the trimmed answer, exiting on Ctrl+C or Ctrl+D.
+fn read(line: rustyline::Result<String>) -> anyhow::Result<String> {
+    match line {
+        Ok(line) => Ok(line.trim_end().to_owned()),
+        Err(ReadlineError::Interrupted) => exit(130),
+        Err(ReadlineError::Eof) => exit(0),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The prompt being answered in [`crate::main()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// The library the current input directory is renamed to, only asked when it was not chosen with the --library flag.
+    Library,
+    /// The series name for the current input directory.
+    Name,
+    /// The files to rename from the current input directory.
+    Selection,
+    /// The season of the selected files of the current input directory.
+    Season,
+    /// The episode list giving the titles of the selected files of the current input directory, only asked when the --titles flag was given.
+    Titles,
+    /// Previewing and executing the changes, once every input directory has been answered.
+    Review,
+}
+
+/// Tracks the input directory and prompt being answered in [`crate::main()`] along with the answers given so far, so that [`Navigation`] commands can move between the prompts.
+///
+/// The episodes of an input directory are only inserted into the database once its last prompt has been answered, and are removed again with [`delete_directory_episodes()`] whenever the directory is revisited or skipped, so the database always matches the answers.
+/// The episodes matched by the rules are inserted right away and are put back whenever the answers of their directory are removed.
+pub struct Flow {
+    /// The input directories given with the -i flag that hold files not matched by the rules.
+    pub directories: Vec<PathBuf>,
+    /// The library of each input directory chosen with the --library flag, as returned by [`route_libraries()`], which are not asked for.
+    pub routes: Vec<Option<usize>>,
+    /// The library chosen for each input directory, if any, as an index into [`Cli::libraries`].
+    pub libraries: Vec<Option<usize>>,
+    /// The series name given for each input directory, if any.
+    pub names: Vec<Option<String>>,
+    /// The selected files of each input directory in the order they will be numbered, if any.
+    pub selections: Vec<Option<Vec<DirEntry>>>,
+    /// What the selected files of each input directory were parsed to, in the same order, when they were selected with a [`crate::mapping::Mapping`].
+    pub mappings: Vec<Option<Vec<Parsed>>>,
+    /// The season given for each input directory, if any.
+    pub seasons: Vec<Option<u32>>,
+    /// The episode titles given for each input directory, if any. Empty when the titles prompt was skipped.
+    pub titles: Vec<Option<Titles>>,
+    /// Index of the input directory being answered. Equal to the amount of directories once [`Step::Review`] is reached.
+    pub index: usize,
+    /// The prompt being answered.
+    pub step: Step,
+    /// The episodes matched by the rules with [`match_directory()`], which are not prompted for.
+    pub matched: Vec<Episode>,
+}
+
+impl Flow {
+    /// Starts the prompts at the first prompt of the first input directory, given the libraries routed with [`route_libraries()`] and the episodes matched by the rules.
+    ///
+    /// If there are no directories left to prompt for, the prompts start at [`Step::Review`].
+    ///
+    /// # Example
+    /// ```
+    /// let mut flow = Flow::new(args.input_paths.clone(), route_libraries(&args)?, Vec::new());
+    /// ```
+    pub fn new(
+        directories: Vec<PathBuf>,
+        routes: Vec<Option<usize>>,
+        matched: Vec<Episode>,
+    ) -> Self {
+        let mut flow = Flow {
+            names: directories.iter().map(|_| None).collect(),
+            selections: directories.iter().map(|_| None).collect(),
+            mappings: directories.iter().map(|_| None).collect(),
+            seasons: directories.iter().map(|_| None).collect(),
+            titles: directories.iter().map(|_| None).collect(),
+            libraries: routes.clone(),
+            routes,
+            directories,
+            index: 0,
+            step: Step::Name,
+            matched,
+        };
+        flow.step = match flow.directories.is_empty() {
+            true => Step::Review,
+            false => flow.first_step(),
+        };
+        flow
+    }
+
+    /// Inserts the episodes matched by the rules into the database, or only the ones of the given input directory.
+    ///
+    /// An episode whose new path was already given to another file in the database, such as one renamed at the prompts, is not inserted and is reported instead.
+    ///
+    /// # Example
+    /// ```
+    /// flow.insert_matched(&db, None).await?;
+    /// ```
+    pub async fn insert_matched(
+        &self,
+        db: &sqlx::SqlitePool,
+        directory: Option<&Path>,
+    ) -> anyhow::Result<()> {
+        let plan = select_plan(db).await?;
+        for episode in self
+            .matched
+            .iter()
+            .filter(|x| directory.is_none_or(|dir| Path::new(&x.old_path).parent() == Some(dir)))
+        {
+            if plan
+                .iter()
+                .any(|x| x.new_path == episode.new_path && x.old_path != episode.old_path)
+            {
+                println!(
+                    "{} matched the rules, but {} is already the new path of another file, so it was skipped.",
+                    episode.old_path.yellow(),
+                    episode.new_path.yellow()
+                );
+                continue;
+            }
+            insert_episode(
+                db,
+                &episode.series_name,
+                episode.season,
+                episode.episode,
+                episode.title.as_deref(),
+                PathBuf::from(&episode.old_path),
+                PathBuf::from(&episode.new_path),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// True if the selected files of the current input directory were all given a season by a [`crate::mapping::Mapping`], in which case the season prompt is skipped.
+    fn mapped_seasons(&self) -> bool {
+        self.mappings[self.index]
+            .as_ref()
+            .is_some_and(|x| x.iter().all(|x| x.season.is_some()))
+    }
+
+    /// Inserts the selected files of the current input directory into the database, in the season answered for it unless a [`crate::mapping::Mapping`] gave them one.
+    ///
+    /// The files are numbered as episodes in the order they were selected, or with the episodes a mapping parsed them to.
+    /// Their titles are taken from the episode list answered for the directory, else from the titles the mapping parsed.
+    ///
+    /// Once the season of every file is known, their new paths are checked against each other and against the episodes already in the database.
+    /// If any of them is already used, nothing is inserted and the conflict is returned as the inner error, so that the selection can be asked again.
+    ///
+    /// # Panics
+    /// If the library, name or selection of the current input directory was not answered yet.
+    pub async fn insert_selection(
+        &self,
+        db: &sqlx::SqlitePool,
+        args: &Cli,
+    ) -> anyhow::Result<Result<(), String>> {
+        let name = self.names[self.index].as_ref().unwrap();
+        let library = &args.libraries[self.libraries[self.index].unwrap()];
+        let mapping = self.mappings[self.index].as_ref();
+        let titles = self.titles[self.index].as_ref();
+        let mut episodes: Vec<Episode> = Vec::new();
+        for (index, file) in self.selections[self.index]
+            .as_ref()
+            .unwrap()
+            .iter()
+            .enumerate()
+        {
+            let parsed = mapping.and_then(|x| x.get(index));
+            let season = parsed
+                .and_then(|x| x.season)
+                .or(self.seasons[self.index])
+                .unwrap_or(1);
+            let episode = parsed.map(|x| x.episode).unwrap_or((index as u32) + 1);
+            let title = titles
+                .and_then(|x| x.get(season, episode))
+                .or(parsed.and_then(|x| x.title.as_deref()));
+            episodes.push(Episode {
+                series_name: name.to_owned(),
+                season,
+                episode,
+                title: title.map(|x| x.to_owned()),
+                old_path: file.path().to_str().unwrap().to_owned(),
+                new_path: episode_path(library, name, season, episode, title, &file.path())
+                    .to_str()
+                    .unwrap()
+                    .to_owned(),
+            });
+        }
+        let plan = select_plan(db).await?;
+        for (index, episode) in episodes.iter().enumerate() {
+            if let Some(other) = episodes[..index]
+                .iter()
+                .chain(&plan)
+                .find(|x| x.new_path == episode.new_path)
+            {
+                return Ok(Err(format!(
+                    "{} and {} would both be renamed to {}.",
+                    other.old_path, episode.old_path, episode.new_path
+                )));
+            }
+        }
+        for episode in episodes {
+            insert_episode(
+                db,
+                &episode.series_name,
+                episode.season,
+                episode.episode,
+                episode.title.as_deref(),
+                PathBuf::from(episode.old_path),
+                PathBuf::from(episode.new_path),
+            )
+            .await?;
+        }
+        Ok(Ok(()))
+    }
+
+    /// Removes the episodes of the given input directory from the database with [`delete_directory_episodes()`], keeping the ones matched by the rules.
+    async fn discard(&self, db: &sqlx::SqlitePool, index: usize) -> anyhow::Result<()> {
+        delete_directory_episodes(db, &self.directories[index]).await?;
+        self.insert_matched(db, Some(&self.directories[index]))
+            .await
+    }
+
+    /// The first prompt of the current input directory, which is [`Step::Library`] unless its library was chosen with the --library flag.
+    fn first_step(&self) -> Step {
+        match self.routes.get(self.index) {
+            Some(None) => Step::Library,
+            _ => Step::Name,
+        }
+    }
+
+    /// The input directory being answered.
+    ///
+    /// # Panics
+    /// If called once [`Step::Review`] has been reached.
+    pub fn path(&self) -> &Path {
+        &self.directories[self.index]
+    }
+
+    /// Moves to [`Step::Titles`] if the --titles flag was given, else inserts the selected files of the current input directory with [`Flow::directory_done()`].
+    async fn selection_done(&mut self, db: &sqlx::SqlitePool, args: &Cli) -> anyhow::Result<()> {
+        match args.titles {
+            true => self.step = Step::Titles,
+            false => self.directory_done(db, args).await?,
+        }
+        Ok(())
+    }
+
+    /// Inserts the selected files of the current input directory with [`Flow::insert_selection()`] and moves to the next directory.
+    ///
+    /// If the new path of a file is already used, the conflict is shown and the files of the directory are asked for again.
+    async fn directory_done(&mut self, db: &sqlx::SqlitePool, args: &Cli) -> anyhow::Result<()> {
+        match self.insert_selection(db, args).await? {
+            Ok(()) => {
+                clearscreen::clear()?;
+                self.next_directory();
+            }
+            Err(e) => {
+                println!("{}", format!("{e} Choose the files again.").red());
+                self.step = Step::Selection;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves to the first prompt of the next input directory, or to [`Step::Review`] if there are none left.
+    pub fn next_directory(&mut self) {
+        self.index += 1;
+        self.step = match self.index < self.directories.len() {
+            true => self.first_step(),
+            false => Step::Review,
+        };
+    }
+
+    /// Moves to the prompt given by a [`Navigation`] command, removing from the database the episodes of any input directory that is revisited or skipped.
+    ///
+    /// - [`Navigation::Back`]: Goes to the previous prompt of the same directory, or to the last answered prompt of the previous directory when at its series name or reviewing the changes.
+    /// - [`Navigation::Skip`]: Discards the answers for the current directory and moves on to the next one.
+    /// - [`Navigation::Restart`]: Discards every answer and goes back to the series name of the first directory.
+    ///
+    /// A command that cannot be followed, such as going back from the first prompt, is reported and the prompt is asked again.
+    ///
+    /// # Example
+    /// ```
+    /// if let Some(navigation) = parse_navigation(&ans) {
+    ///     flow.navigate(&db, navigation).await?;
+    /// }
+    /// ```
+    pub async fn navigate(
+        &mut self,
+        db: &sqlx::SqlitePool,
+        navigation: Navigation,
+    ) -> anyhow::Result<()> {
+        match navigation {
+            Navigation::Restart => {
+                delete_all_episodes(db).await?;
+                *self = Flow::new(
+                    std::mem::take(&mut self.directories),
+                    std::mem::take(&mut self.routes),
+                    std::mem::take(&mut self.matched),
+                );
+                self.insert_matched(db, None).await?;
+            }
+            Navigation::Skip => match self.step {
+                Step::Review => println!("There is no directory left to skip."),
+                _ => {
+                    self.discard(db, self.index).await?;
+                    self.libraries[self.index] = self.routes[self.index];
+                    self.names[self.index] = None;
+                    self.selections[self.index] = None;
+                    self.mappings[self.index] = None;
+                    self.seasons[self.index] = None;
+                    self.titles[self.index] = None;
+                    self.next_directory();
+                }
+            },
+            Navigation::Back => match self.step {
+                Step::Name if self.first_step() == Step::Library => self.step = Step::Library,
+                Step::Selection => self.step = Step::Name,
+                Step::Season => self.step = Step::Selection,
+                Step::Titles if self.mapped_seasons() => self.step = Step::Selection,
+                Step::Titles => self.step = Step::Season,
+                Step::Library | Step::Name | Step::Review if self.index == 0 => {
+                    println!("This is the first prompt.")
+                }
+                Step::Library | Step::Name | Step::Review => {
+                    self.index -= 1;
+                    self.discard(db, self.index).await?;
+                    self.step = match (
+                        &self.libraries[self.index],
+                        &self.names[self.index],
+                        &self.selections[self.index],
+                    ) {
+                        (None, _, _) => Step::Library,
+                        (_, None, _) => Step::Name,
+                        (_, _, None) => Step::Selection,
+                        _ if self.titles[self.index].is_some() => Step::Titles,
+                        _ if self.mapped_seasons() => Step::Selection,
+                        _ => Step::Season,
+                    };
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Looks up a series name in the [`Metadata`], showing what is known about the show and offering its canonical name, as given by [`crate::metadata::MetadataShow::canonical_name()`], if the series name is written differently.
+///
+/// Returns the series name to use, which is the given one unless the user accepts the canonical name, or the [`Navigation`] typed instead of answering, as found by [`parse_navigation()`].
+fn metadata_name(
+    prompter: &mut Prompter,
+    metadata: &Metadata,
+    name: String,
+) -> anyhow::Result<Result<String, Navigation>> {
+    let shows = metadata.find(&name);
+    let show = match shows[..] {
+        [] => return Ok(Ok(name)),
+        [show] => show,
+        _ => {
+            println!(
+                "Several shows are named {} in the metadata: {}. Type {} and the name with its year to use one of them.",
+                name.yellow(),
+                shows
+                    .iter()
+                    .map(|x| x.canonical_name())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                ":back".yellow()
+            );
+            return Ok(Ok(name));
+        }
+    };
+    let canonical = show.canonical_name();
+    println!(
+        "{} is in the metadata, with {} seasons and {} episodes.",
+        canonical.green(),
+        show.seasons.to_string().green(),
+        show.episodes.to_string().green()
+    );
+    if name == show.name || name == canonical || !valid_name(&canonical) {
+        return Ok(Ok(name));
+    }
+    println!(
+        "Would you like to name the series {} instead [y/n]:",
+        canonical.green()
+    );
+    let ans = prompter.answer()?;
+    if let Some(navigation) = parse_navigation(&ans) {
+        return Ok(Err(navigation));
+    }
+    Ok(Ok(match ans.eq_ignore_ascii_case("y") {
+        true => canonical,
+        false => name,
+    }))
+}
+
+/// Iterates through the input paths and prompts the user for what series name would they like the files to correspond to.
+/// The files matched by the rules with [`match_directory()`] are added to the database right away, and only the remaining files are prompted for, skipping the input paths left without any.
+/// If there is more than one [`Library`] and the library of the input path was not chosen with the --library flag, the user is first asked which library the files should be renamed to, by name or number.
+/// The prompts are read with [`Prompter`], which completes series names with the folders already present in the libraries and remembers the names given in previous runs.
+/// If the user inputs an invalid name, it will prompt the user again to enter a name until a valid name is given.
+/// If show metadata was imported with `rplexfmtr import-metadata`, the canonical names of its shows are offered as completions as well, and a series name found in it with [`Metadata::find()`] is checked with [`metadata_name()`].\
+///
+/// Once a valid name is provided, the user is prompted to choose which files they would like to be renamed.
+/// The amount of episodes of every season of the show is shown along with the files if the show is in the metadata.
+/// If the --picker flag was given, the files are chosen with [`crate::picker::pick_files()`] and the prompt is only shown if the picker is left without a selection.
+/// If part of the selection was ignored by [`parse_range()`], the ignored parts are shown and the user is asked to either continue with the selection or enter it again.
+/// The selected files are numbered as episodes following the [`SelectionOrder`] given by the --order flag.
+/// Instead of a range, a regular expression can be typed after `re:`, whose named groups give the season, episode and title of each file as described in [`crate::mapping::Mapping`]. What each file was parsed to is shown with [`mapping_preview()`], and the parsed files are selected once the user accepts it.
+/// Then, the user is prompted for the season of the selected files, unless the mapping gave every one of them a season. Once again, if the user provides an invalid input, they will be prompted to input a season number again unit a valid input is provided.
+/// If the --titles flag was given, the user is then asked for the titles of the episodes, either as the path of a CSV or TXT episode list read with [`load_titles()`] or as pasted lines read with [`parse_titles()`], which is skipped by pressing enter, or uses the titles of the show from the metadata if it has any. The titles of the mapping are used for the episodes the list has no title for.\
+///
+/// Then, the validated set of files will be inputted to the database.
+/// If more than one input directory was provided, the user will be prompted to input the information for that set of files, repeating the process.\
+///
+/// At any prompt, the user can type `:back`, `:skip` or `:restart` instead of an answer to go back to the previous prompt, skip the current directory or start over, as described in [`Flow::navigate()`].
+/// The episodes of a directory whose answers are revised or that is skipped are removed from the database.\
+///
+/// Once all input directories have been processed, the user will be prompted to preview the changes.
+/// If the user inputs 'y', a table containing the series_name, season, episode, current_path and new_path will be displayed to the user.
+/// Else, it does not display the changes.\
+///
+/// Finally, the user will be prompted if they would like to execute the changes.
+/// If the user inputs 'e', individual rows of the changes can be edited with [`Prompter::edit_plan()`] before being asked again.
+/// Returns true if the user inputs 'y', leaving the database ready for [`crate::files::move_files()`], else returns false.
+///
+/// # Example
+/// ```
+/// if run_prompts(&db, &args).await? {
+///     move_files(&db, &args).await?;
+/// }
+/// ```
+pub async fn run_prompts(db: &sqlx::SqlitePool, args: &Cli) -> anyhow::Result<bool> {
+    let mut shows = Vec::new();
+    for library in &args.libraries {
+        for show in get_show_names(&library.path)? {
+            if !shows.contains(&show) {
+                shows.push(show);
+            }
+        }
+    }
+    let metadata = Metadata::open().await;
+    for show in metadata.iter().flat_map(|x| &x.shows) {
+        let name = show.canonical_name();
+        if valid_name(&name) && !shows.contains(&name) {
+            shows.push(name);
+        }
+    }
+    shows.sort_by(|a, b| natord::compare(&a.to_ascii_lowercase(), &b.to_ascii_lowercase()));
+    let mut prompter = Prompter::new(shows)?;
+    let mut directories = Vec::new();
+    let mut routes = Vec::new();
+    let mut matched = Vec::new();
+    for (path, route) in args.input_paths.iter().zip(route_libraries(args)?) {
+        let (episodes, files, conflicts) = match_directory(args, path, route, &matched)?;
+        if !files.is_empty() {
+            directories.push(path.clone());
+            routes.push(route);
+        }
+        matched.extend(episodes);
+        for episode in conflicts {
+            println!(
+                "{} matched the rules, but {} is already the new path of another file, so it will be prompted for.",
+                episode.old_path.yellow(),
+                episode.new_path.yellow()
+            );
+        }
+    }
+    if !matched.is_empty() {
+        println!(
+            "{} files were matched by the rules and will not be prompted for.",
+            matched.len().to_string().green()
+        );
+    }
+    let mut flow = Flow::new(directories, routes, matched);
+    flow.insert_matched(db, None).await?;
+    println!(
+        "Type {}, {} or {} at any prompt to go back, skip a directory or start over.",
+        ":back".yellow(),
+        ":skip".yellow(),
+        ":restart".yellow()
+    );
+    loop {
+        let navigation = match flow.step {
+            Step::Library => {
+                println!(
+                    "Which library would you like the entries for {} to be renamed to?\n{}",
+                    flow.path().to_str().unwrap().green(),
+                    library_list(&args.libraries)
+                );
+                let ans = prompter.answer()?;
+                let navigation = parse_navigation(&ans);
+                if navigation.is_none() {
+                    if let Some(library) = find_library(&args.libraries, &ans) {
+                        flow.libraries[flow.index] = Some(library);
+                        flow.step = Step::Name;
+                    }
+                }
+                navigation
+            }
+            Step::Name => {
+                println!(
+                    "What would you like the entries for {} to be titled?: ",
+                    flow.path().to_str().unwrap().green()
+                );
+                let ans = prompter.series_name()?;
+                let mut navigation = parse_navigation(&ans);
+                if navigation.is_none() && valid_name(&ans) {
+                    let name = match &metadata {
+                        Some(metadata) => metadata_name(&mut prompter, metadata, ans)?,
+                        None => Ok(ans),
+                    };
+                    match name {
+                        Ok(name) => {
+                            flow.names[flow.index] = Some(name);
+                            flow.step = Step::Selection;
+                        }
+                        Err(typed) => navigation = Some(typed),
+                    }
+                }
+                navigation
+            }
+            Step::Selection => {
+                let path = flow.path().to_path_buf();
+                let files = match_directory(args, &path, flow.routes[flow.index], &flow.matched)?.1;
+                let names = get_file_names(&files)?;
+                let mut files_numbers: Vec<usize> = Vec::new();
+                let mut parsed: Vec<Parsed> = Vec::new();
+                let mut navigation = None;
+                if args.picker {
+                    if let Some(selection) = pick_files(&path, names.clone())? {
+                        files_numbers = selection.indexes;
+                        parsed = selection.parsed;
+                    }
+                }
+                if files_numbers.is_empty() {
+                    print_directory(&files)?;
+                    let show = metadata.as_ref().and_then(|metadata| {
+                        Some((metadata, metadata.lookup(flow.names[flow.index].as_ref()?)?))
+                    });
+                    if let Some((metadata, show)) = show {
+                        println!(
+                            "Episodes per season of {} in the metadata: {}",
+                            show.canonical_name().green(),
+                            metadata
+                                .season_episodes(show)
+                                .await?
+                                .iter()
+                                .map(|(season, episodes)| format!("S{season}: {episodes}"))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        );
+                    }
+                }
+                while files_numbers.is_empty() {
+                    println!("Which files would you like to choose?");
+                    let ans = prompter.answer()?;
+                    navigation = parse_navigation(&ans);
+                    if navigation.is_some() {
+                        break;
+                    }
+                    if let Some(mapping) = parse_mapping(&ans) {
+                        let mapping = match mapping {
+                            Ok(mapping) => mapping,
+                            Err(e) => {
+                                println!("{}", format!("Invalid regular expression: {e}").red());
+                                continue;
+                            }
+                        };
+                        let results = mapping.map_files(&names);
+                        print!("{}", mapping_preview(&names, &results));
+                        if results.iter().all(|x| x.is_err()) {
+                            println!("{}", "No file was parsed by the regular expression.".red());
+                            continue;
+                        }
+                        println!("Would you like to use this mapping [y/n]:");
+                        let ans = prompter.answer()?;
+                        navigation = parse_navigation(&ans);
+                        if navigation.is_some() {
+                            break;
+                        }
+                        if ans.eq_ignore_ascii_case("y") {
+                            for (index, result) in results.into_iter().enumerate() {
+                                if let Ok(result) = result {
+                                    files_numbers.push(index);
+                                    parsed.push(result);
+                                }
+                            }
+                        }
+                        continue;
+                    }
+                    let selection = parse_range(files.len(), ans)?;
+                    if !selection.ignored.is_empty() {
+                        print_ignored(&selection, files.len());
+                        println!("Would you like to continue with this selection [y/n]:");
+                        let ans = prompter.answer()?;
+                        navigation = parse_navigation(&ans);
+                        if navigation.is_some() {
+                            break;
+                        }
+                        if !ans.eq_ignore_ascii_case("y") {
+                            continue;
+                        }
+                    }
+                    files_numbers = selection.indexes;
+                }
+                if navigation.is_none() {
+                    let mut files = files.into_iter().map(Some).collect::<Vec<_>>();
+                    let mut selected_files: Vec<_> = files_numbers
+                        .iter()
+                        .filter_map(|index| files[*index].take())
+                        .collect();
+                    if args.order == SelectionOrder::Natural && parsed.is_empty() {
+                        selected_files.sort_by(|a, b| {
+                            natord::compare(
+                                a.file_name().to_ascii_lowercase().to_str().unwrap(),
+                                b.file_name().to_ascii_lowercase().to_str().unwrap(),
+                            )
+                        });
+                    }
+                    flow.selections[flow.index] = Some(selected_files);
+                    flow.mappings[flow.index] = match parsed.is_empty() {
+                        true => None,
+                        false => Some(parsed),
+                    };
+                    match flow.mapped_seasons() {
+                        true => flow.selection_done(db, args).await?,
+                        false => flow.step = Step::Season,
+                    }
+                }
+                navigation
+            }
+            Step::Season => {
+                println!("What season do these files belong to?");
+                let ans = prompter.answer()?;
+                let navigation = parse_navigation(&ans);
+                if let (None, Ok(season)) = (navigation, ans.parse::<u32>()) {
+                    flow.seasons[flow.index] = Some(season);
+                    flow.selection_done(db, args).await?;
+                }
+                navigation
+            }
+            Step::Titles => {
+                let show = metadata.as_ref().and_then(|metadata| {
+                    Some((metadata, metadata.lookup(flow.names[flow.index].as_ref()?)?))
+                });
+                let known = match show {
+                    Some((metadata, show)) => metadata.titles(show).await?,
+                    None => Titles::default(),
+                };
+                let skip = match (known.is_empty(), show) {
+                    (false, Some((_, show))) => format!(
+                        "press enter to use the {} titles of {} from the metadata",
+                        known.len().to_string().green(),
+                        show.canonical_name().green()
+                    ),
+                    _ => "press enter to skip".to_owned(),
+                };
+                println!(
+                    "Where are the titles of these episodes? Type the path of a CSV or TXT episode list, paste one title per line followed by an empty line, or {skip}:"
+                );
+                let mut ans = prompter.answer()?;
+                let navigation = parse_navigation(&ans);
+                if navigation.is_none() {
+                    let titles = match Path::new(&ans).is_file() {
+                        _ if ans.is_empty() => Ok(known),
+                        true => load_titles(Path::new(&ans)),
+                        false => {
+                            let mut line = ans.clone();
+                            while !line.is_empty() {
+                                line = prompter.answer()?;
+                                ans = ans + "\n" + &line;
+                            }
+                            parse_titles(&ans, false).map_err(anyhow::Error::msg)
+                        }
+                    };
+                    match titles {
+                        Ok(titles) if titles.is_empty() && !ans.is_empty() => {
+                            println!("{}", "No valid title was given.".red())
+                        }
+                        Ok(titles) => {
+                            flow.titles[flow.index] = Some(titles);
+                            flow.directory_done(db, args).await?;
+                        }
+                        Err(e) => println!("{}", e.to_string().red()),
+                    }
+                }
+                navigation
+            }
+            Step::Review => {
+                println!("Would you like to preview the changes [y/n]:");
+                let ans = prompter.answer()?;
+                let mut navigation = parse_navigation(&ans);
+                if navigation.is_none() {
+                    if ans.eq_ignore_ascii_case("y") {
+                        preview_changes(db, args).await?;
+                    }
+                    println!("Would you like to execute these changes [y/n], or edit them [e]:");
+                    let ans = prompter.answer()?;
+                    navigation = parse_navigation(&ans);
+                    if navigation.is_none() {
+                        if ans.eq_ignore_ascii_case("e") {
+                            navigation = prompter.edit_plan(db, args).await?;
+                        } else {
+                            return Ok(ans.eq_ignore_ascii_case("y"));
+                        }
+                    }
+                }
+                navigation
+            }
+        };
+        if let Some(navigation) = navigation {
+            flow.navigate(db, navigation).await?;
+        }
+    }
+}
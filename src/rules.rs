@@ -0,0 +1,455 @@
+use crate::database::Episode;
+use crate::files::{episode_path, get_files, Cli};
+use crate::titles::sanitize_title;
+use crate::validate::valid_name;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::fmt::Display;
+use std::fs::DirEntry;
+use std::path::{Path, PathBuf};
+
+/// A capture group of the pattern of a [`Rule`], given by its number or its name.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Group {
+    /// The number of the group, `1` being the first one.
+    Index(usize),
+    /// The name of a named group, such as `episode` for `(?P<episode>\d+)`.
+    Name(String),
+}
+
+impl Display for Group {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Group::Index(index) => write!(f, "{index}"),
+            Group::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl Group {
+    /// Returns the text captured by the group, if it took part in the match.
+    fn get<'a>(&self, captures: &Captures<'a>) -> Option<&'a str> {
+        match self {
+            Group::Index(index) => captures.get(*index),
+            Group::Name(name) => captures.name(name),
+        }
+        .map(|x| x.as_str())
+    }
+
+    /// Returns true if the given pattern has this group.
+    fn exists(&self, regex: &Regex) -> bool {
+        match self {
+            Group::Index(index) => *index > 0 && *index < regex.captures_len(),
+            Group::Name(name) => regex.capture_names().flatten().any(|x| x == name),
+        }
+    }
+}
+
+/// The settings of a rule in the rules file, given as a `[[rule]]` table.
+///
+/// # Example
+/// ```toml
+/// [[rule]]
+/// pattern = '^\[SubsPlease\] (Sousou no )?Frieren - (\d+)'
+/// series = "Frieren"
+/// season = 1
+/// episode = 2
+/// library = "Anime"
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    /// Regular expression the file names are matched against.
+    pub pattern: String,
+    /// Series of the matched files.
+    pub series: Option<String>,
+    /// Group capturing the series of the matched files, used instead of series.
+    pub series_group: Option<Group>,
+    /// Season of the matched files, `1` by default.
+    pub season: Option<u32>,
+    /// Group capturing the season of the matched files, used instead of season.
+    pub season_group: Option<Group>,
+    /// Group capturing the episode number of the matched files.
+    pub episode: Group,
+    /// Group capturing the title of the matched files, if any.
+    pub title: Option<Group>,
+    /// Name of the library the matched files are renamed to, the library of their input directory by default.
+    pub library: Option<String>,
+}
+
+/// The rules file, holding a list of [`RuleConfig`]s.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RulesFile {
+    /// The rules, in the order they are tried.
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<RuleConfig>,
+}
+
+/// A rule of the rules file, loaded by [`load_rules()`], matching file names to the episode they will be renamed to.
+#[derive(Clone, Debug)]
+pub struct Rule {
+    /// The compiled pattern of the rule.
+    pub regex: Regex,
+    /// The settings of the rule, checked against the pattern by [`load_rules()`].
+    pub config: RuleConfig,
+}
+
+/// The episode a file name was matched to by [`match_rules()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleMatch {
+    /// Number of the rule that matched, `1` being the first rule of the file.
+    pub rule: usize,
+    /// The series name.
+    pub series_name: String,
+    /// The season number.
+    pub season: u32,
+    /// The episode number.
+    pub episode: u32,
+    /// The title captured by the title group of the rule, if any.
+    pub title: Option<String>,
+    /// The name of the library given by the rule, if any.
+    pub library: Option<String>,
+}
+
+/// Returns the path of the rules file, which is the one given with the --rules flag or the `RPLEXFMTR_RULES` environment variable, else `rplexfmtr/rules.toml` inside of the config directory of the user.
+pub fn rules_path(args: &Cli) -> Option<PathBuf> {
+    args.rules_file
+        .clone()
+        .or_else(|| dirs::config_dir().map(|x| x.join("rplexfmtr").join("rules.toml")))
+}
+
+/// Checks the settings of a rule against its pattern and the libraries, returning the compiled [`Rule`].
+fn compile_rule(args: &Cli, config: RuleConfig) -> Result<Rule, String> {
+    let regex = Regex::new(&config.pattern).map_err(|e| e.to_string())?;
+    let groups = [
+        ("series_group", &config.series_group),
+        ("season_group", &config.season_group),
+        ("episode", &Some(config.episode.clone())),
+        ("title", &config.title),
+    ];
+    for (setting, group) in groups {
+        if let Some(group) = group {
+            if !group.exists(&regex) {
+                return Err(format!("the pattern has no group {group} for {setting}"));
+            }
+        }
+    }
+    match (&config.series, &config.series_group) {
+        (Some(series), None) if !valid_name(series) => {
+            return Err(format!("{series} is not a valid series name"))
+        }
+        (Some(_), None) | (None, Some(_)) => {}
+        _ => return Err("give either series or series_group".to_owned()),
+    }
+    if config.season.is_some() && config.season_group.is_some() {
+        return Err("give either season or season_group".to_owned());
+    }
+    if let Some(library) = &config.library {
+        if !args.libraries.iter().any(|x| {
+            x.name
+                .as_ref()
+                .is_some_and(|x| x.eq_ignore_ascii_case(library))
+        }) {
+            return Err(format!(
+                "there is no library named {library} in the config file"
+            ));
+        }
+    }
+    Ok(Rule { regex, config })
+}
+
+/// Loads the rules file given by [`rules_path()`], checking every rule with the libraries already loaded into [`Cli::libraries`].
+///
+/// A missing file means there are no rules, unless it was given explicitly with the --rules flag.
+///
+/// # Errors
+/// If the file cannot be read or is not valid TOML, or if a rule has an invalid pattern, a group its pattern does not have, both or neither of series and series_group, both season and season_group, an invalid series name or a library that does not exist.
+///
+/// # Example
+/// ```
+/// args.rules = load_rules(&args)?;
+/// ```
+pub fn load_rules(args: &Cli) -> anyhow::Result<Vec<Rule>> {
+    let Some(path) = rules_path(args) else {
+        return Ok(Vec::new());
+    };
+    if args.rules_file.is_none() && !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Could not read the rules file {}: {e}", path.display()))?;
+    let file: RulesFile = toml::from_str(&contents)
+        .map_err(|e| anyhow::anyhow!("Invalid rules file {}: {e}", path.display()))?;
+    file.rules
+        .into_iter()
+        .enumerate()
+        .map(|(index, config)| {
+            compile_rule(args, config).map_err(|e| {
+                anyhow::anyhow!("Invalid rule {} in {}: {e}", index + 1, path.display())
+            })
+        })
+        .collect()
+}
+
+/// Matches a file name against the rules in order, returning the episode given by the first rule that matches.
+///
+/// A rule only matches if its pattern matches the file name and its groups capture numbers for the season and episode and a valid series name, as checked by [`valid_name()`]. Dots and underscores captured for the series and title are replaced with spaces, and the title is sanitized with [`sanitize_title()`].
+///
+/// # Example
+/// ```
+/// let matched = match_rules(&args.rules, "[SubsPlease] Frieren - 07 (1080p).mkv").unwrap();
+/// assert_eq!((matched.series_name.as_str(), matched.season, matched.episode), ("Frieren", 1, 7));
+/// ```
+pub fn match_rules(rules: &[Rule], file_name: &str) -> Option<RuleMatch> {
+    rules.iter().enumerate().find_map(|(index, rule)| {
+        let captures = rule.regex.captures(file_name)?;
+        let config = &rule.config;
+        let series_name = match (&config.series, &config.series_group) {
+            (Some(series), _) => series.clone(),
+            (None, Some(group)) => group
+                .get(&captures)?
+                .replace(['.', '_'], " ")
+                .trim()
+                .to_owned(),
+            (None, None) => return None,
+        };
+        let season = match &config.season_group {
+            Some(group) => group.get(&captures)?.parse().ok()?,
+            None => config.season.unwrap_or(1),
+        };
+        if !valid_name(&series_name) {
+            return None;
+        }
+        Some(RuleMatch {
+            rule: index + 1,
+            series_name,
+            season,
+            episode: config.episode.get(&captures)?.parse().ok()?,
+            title: config
+                .title
+                .as_ref()
+                .and_then(|x| x.get(&captures))
+                .and_then(|x| sanitize_title(&x.replace(['.', '_'], " "))),
+            library: config.library.clone(),
+        })
+    })
+}
+
+/// Matches a file with [`match_rules()`] and returns the episode it will be renamed to, in the library given by the rule or else the given library.
+///
+/// Returns [`None`] if no rule matches or if the rule names no library and none is given.
+///
+/// # Example
+/// ```
+/// let episode = rule_episode(&args, Some(0), Path::new("/home/user/downloads/[SubsPlease] Frieren - 07.mkv"));
+/// ```
+pub fn rule_episode(args: &Cli, library: Option<usize>, file: &Path) -> Option<Episode> {
+    let matched = match_rules(&args.rules, file.file_name()?.to_str()?)?;
+    let library = match &matched.library {
+        Some(name) => args.libraries.iter().find(|x| {
+            x.name
+                .as_ref()
+                .is_some_and(|x| x.eq_ignore_ascii_case(name))
+        })?,
+        None => &args.libraries[library?],
+    };
+    Some(Episode {
+        new_path: episode_path(
+            library,
+            &matched.series_name,
+            matched.season,
+            matched.episode,
+            matched.title.as_deref(),
+            file,
+        )
+        .to_str()?
+        .to_owned(),
+        old_path: file.to_str()?.to_owned(),
+        series_name: matched.series_name,
+        season: matched.season,
+        episode: matched.episode,
+        title: matched.title,
+    })
+}
+
+/// Splits the files of an input directory, as returned by [`get_files()`], into the episodes matched by the rules with [`rule_episode()`] and the files left for the prompts.
+///
+/// The given library is the one the directory was routed to with [`crate::library::route_libraries()`], if any.
+/// A file renamed to the same path as a previous file of the directory, or as another file in the taken episodes, such as the ones matched in other directories, is left for the prompts as well.
+/// These conflicting episodes are returned as the third element so that they can be reported.
+///
+/// # Errors
+/// If the directory cannot be read.
+///
+/// # Example
+/// ```
+/// let (episodes, files, conflicts) = match_directory(&args, &args.input_paths[0], routes[0], &[])?;
+/// ```
+pub fn match_directory(
+    args: &Cli,
+    path: &Path,
+    library: Option<usize>,
+    taken: &[Episode],
+) -> anyhow::Result<(Vec<Episode>, Vec<DirEntry>, Vec<Episode>)> {
+    let mut episodes: Vec<Episode> = Vec::new();
+    let mut files = Vec::new();
+    let mut conflicts = Vec::new();
+    for file in get_files(path.to_path_buf(), &args.extensions)? {
+        let Some(episode) = rule_episode(args, library, &file.path()) else {
+            files.push(file);
+            continue;
+        };
+        match episodes
+            .iter()
+            .chain(taken)
+            .any(|x| x.new_path == episode.new_path && x.old_path != episode.old_path)
+        {
+            true => {
+                conflicts.push(episode);
+                files.push(file);
+            }
+            false => episodes.push(episode),
+        }
+    }
+    Ok((episodes, files, conflicts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::Rplexfmtr;
+    use crate::library::Library;
+    use clap::Parser;
+
+    fn args() -> Cli {
+        let mut args = Rplexfmtr::try_parse_from(["rplexfmtr", "--plan-in", "plan.json"])
+            .unwrap()
+            .args;
+        args.libraries.push(Library {
+            name: Some("Anime".to_owned()),
+            path: PathBuf::from("/anime"),
+            template: "{series} S{season}E{episode}".to_owned(),
+            padding: 0,
+        });
+        args
+    }
+
+    fn rules(toml: &str) -> Result<Vec<Rule>, String> {
+        let file: RulesFile = toml::from_str(toml).map_err(|e| e.to_string())?;
+        file.rules
+            .into_iter()
+            .map(|x| compile_rule(&args(), x))
+            .collect()
+    }
+
+    fn matched(rules: &[Rule], file_name: &str) -> Option<(usize, String, u32, u32)> {
+        match_rules(rules, file_name).map(|x| (x.rule, x.series_name, x.season, x.episode))
+    }
+
+    #[test]
+    fn match_rules_uses_fixed_settings() {
+        let rules = rules(
+            r#"
+            [[rule]]
+            pattern = '^\[SubsPlease\] (Sousou no )?Frieren - (\d+)'
+            series = "Frieren"
+            episode = 2
+            library = "anime"
+            "#,
+        )
+        .unwrap();
+        let found = match_rules(&rules, "[SubsPlease] Frieren - 07 (1080p).mkv").unwrap();
+        assert_eq!(
+            (found.series_name.as_str(), found.season, found.episode),
+            ("Frieren", 1, 7)
+        );
+        assert_eq!(found.library.as_deref(), Some("anime"));
+        assert_eq!(matched(&rules, "Frieren - 07.mkv"), None);
+    }
+
+    #[test]
+    fn match_rules_uses_groups() {
+        let rules = rules(
+            r#"
+            [[rule]]
+            pattern = '^(?P<show>.+?)\.S(?P<season>\d+)E(?P<episode>\d+)\.(?P<title>.+)\.\w+$'
+            series_group = "show"
+            season_group = "season"
+            episode = "episode"
+            title = "title"
+            "#,
+        )
+        .unwrap();
+        let found = match_rules(&rules, "The_Show.S02E03.Who.Are.You?.mkv").unwrap();
+        assert_eq!(
+            (found.series_name.as_str(), found.season, found.episode),
+            ("The Show", 2, 3)
+        );
+        assert_eq!(found.title.as_deref(), Some("Who Are You"));
+    }
+
+    #[test]
+    fn match_rules_uses_the_first_rule_that_matches() {
+        let rules = rules(
+            r#"
+            [[rule]]
+            pattern = '^A (\d+)'
+            series = "First"
+            episode = 1
+
+            [[rule]]
+            pattern = '(\d+)'
+            series = "Second"
+            season = 2
+            episode = 1
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            matched(&rules, "A 3.mkv"),
+            Some((1, "First".to_owned(), 1, 3))
+        );
+        assert_eq!(
+            matched(&rules, "B 4.mkv"),
+            Some((2, "Second".to_owned(), 2, 4))
+        );
+        assert_eq!(matched(&rules, "B.mkv"), None);
+    }
+
+    #[test]
+    fn match_rules_skips_invalid_captures() {
+        let rules = rules(
+            r#"
+            [[rule]]
+            pattern = '^(.*) - (\w+)'
+            series_group = 1
+            episode = 2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(matched(&rules, "Show - one.mkv"), None);
+        assert_eq!(matched(&rules, "Sh:ow - 1.mkv"), None);
+        assert_eq!(
+            matched(&rules, "Show - 1.mkv"),
+            Some((1, "Show".to_owned(), 1, 1))
+        );
+    }
+
+    #[test]
+    fn compile_rule_rejects_invalid_rules() {
+        for rule in [
+            "pattern = '('\nseries = 'S'\nepisode = 1",
+            "pattern = '(\\d+)'\nseries = 'S'\nepisode = 2",
+            "pattern = '(?P<e>\\d+)'\nseries = 'S'\nepisode = 'x'",
+            "pattern = '(\\d+)'\nepisode = 1",
+            "pattern = '(.+) (\\d+)'\nseries = 'S'\nseries_group = 1\nepisode = 2",
+            "pattern = '(\\d+) (\\d+)'\nseries = 'S'\nseason = 1\nseason_group = 1\nepisode = 2",
+            "pattern = '(\\d+)'\nseries = 'S/T'\nepisode = 1",
+            "pattern = '(\\d+)'\nseries = 'S'\nepisode = 1\nlibrary = 'Movies'",
+        ] {
+            assert!(rules(&format!("[[rule]]\n{rule}")).is_err(), "{rule}");
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::files::{border_color, get_file_names, get_files, Cli};
+use crate::rules::{match_rules, Rule};
+use colored::*;
+use once_cell::unsync::Lazy;
+use regex::Regex;
+use std::path::PathBuf;
+use tabled::Tabled;
+
+/// The series, season and episode guessed by [`guess_episode()`] from the name of a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Guess {
+    /// The series name, with dots and underscores replaced with spaces. Empty if the name starts with the season and episode.
+    pub series_name: String,
+    /// The season number.
+    pub season: u32,
+    /// The episode number.
+    pub episode: u32,
+}
+
+/// Guesses the series, season and episode of a file from its name, following the usual `Show.Name.S01E02` and `Show Name 1x02` conventions.
+///
+/// Returns [`None`] if the name has neither.
+///
+/// # Example
+/// ```
+/// let guess = guess_episode("The.Show.S01E02.1080p.mkv").unwrap();
+/// assert_eq!((guess.series_name.as_str(), guess.season, guess.episode), ("The Show", 1, 2));
+/// ```
+pub fn guess_episode(file_name: &str) -> Option<Guess> {
+    let regex: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"(?i)^(?P<series>.*?)[\s._-]*(?:s(?P<season>\d{1,3})[\s._-]*e(?P<episode>\d{1,4})|\b(?P<season_x>\d{1,2})x(?P<episode_x>\d{2,3})\b)",
+        )
+        .unwrap()
+    });
+    let captures = regex.captures(file_name)?;
+    let number = |a: &str, b: &str| {
+        captures
+            .name(a)
+            .or_else(|| captures.name(b))
+            .and_then(|x| x.as_str().parse().ok())
+    };
+    Some(Guess {
+        series_name: captures["series"]
+            .replace(['.', '_'], " ")
+            .trim()
+            .to_owned(),
+        season: number("season", "season_x")?,
+        episode: number("episode", "episode_x")?,
+    })
+}
+
+/// A row of the table printed by `rplexfmtr scan`.
+#[derive(Clone, Debug, Tabled)]
+pub struct ScanRow {
+    /// The input folder the file is in.
+    pub folder: String,
+    /// The name of the file.
+    pub file: String,
+    /// The guessed series name, empty if nothing was guessed.
+    pub series_name: String,
+    /// The guessed season number, empty if nothing was guessed.
+    pub season: String,
+    /// The guessed episode number, empty if nothing was guessed.
+    pub episode: String,
+    /// How the episode was found, either `rule <number>` for the rule of the rules file that matched or `name` for [`guess_episode()`], empty if nothing was guessed.
+    pub matched_by: String,
+}
+
+/// Lists the files of the given input folders, as returned by [`get_files()`], along with the series, season and episode matched by the given rules with [`match_rules()`], or else guessed by [`guess_episode()`].
+///
+/// # Errors
+/// If one of the folders cannot be read.
+///
+/// # Example
+/// ```
+/// let rows = scan(&[PathBuf::from("/home/user/downloads")], &args.extensions, &args.rules)?;
+/// println!("{}", tabled::Table::new(rows));
+/// ```
+pub fn scan(
+    paths: &[PathBuf],
+    extensions: &[String],
+    rules: &[Rule],
+) -> anyhow::Result<Vec<ScanRow>> {
+    let mut rows = Vec::new();
+    for path in paths {
+        for file in get_file_names(&get_files(path.to_owned(), extensions)?)? {
+            let (guess, matched_by) = match match_rules(rules, &file) {
+                Some(matched) => (
+                    Some(Guess {
+                        series_name: matched.series_name,
+                        season: matched.season,
+                        episode: matched.episode,
+                    }),
+                    format!("rule {}", matched.rule),
+                ),
+                None => {
+                    let guess = guess_episode(&file);
+                    let matched_by = match guess.is_some() {
+                        true => "name".to_owned(),
+                        false => String::new(),
+                    };
+                    (guess, matched_by)
+                }
+            };
+            rows.push(ScanRow {
+                folder: path.display().to_string(),
+                series_name: guess
+                    .as_ref()
+                    .map(|x| x.series_name.clone())
+                    .unwrap_or_default(),
+                season: guess
+                    .as_ref()
+                    .map(|x| x.season.to_string())
+                    .unwrap_or_default(),
+                episode: guess
+                    .as_ref()
+                    .map(|x| x.episode.to_string())
+                    .unwrap_or_default(),
+                matched_by,
+                file,
+            });
+        }
+    }
+    Ok(rows)
+}
+
+/// Prints the files of the given input folders as a table along with the series, season and episode matched by the rules or guessed from their names, as returned by [`scan()`].
+///
+/// # Errors
+/// If one of the folders cannot be read.
+pub fn print_scan(args: &Cli, paths: &[PathBuf]) -> anyhow::Result<()> {
+    let rows = scan(paths, &args.extensions, &args.rules)?;
+    let guessed = rows.iter().filter(|x| !x.episode.is_empty()).count();
+    println!(
+        "{}",
+        tabled::Table::new(&rows)
+            .with(tabled::settings::Style::rounded())
+            .with(border_color(tabled::settings::Color::FG_GREEN))
+    );
+    println!(
+        "The episode of {} of {} files was matched by the rules or guessed from their names.",
+        guessed.to_string().green(),
+        rows.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guess(file_name: &str) -> Option<(String, u32, u32)> {
+        guess_episode(file_name).map(|x| (x.series_name, x.season, x.episode))
+    }
+
+    #[test]
+    fn guess_episode_reads_season_and_episode() {
+        assert_eq!(
+            guess("The.Show.S01E02.1080p.mkv"),
+            Some(("The Show".to_owned(), 1, 2))
+        );
+        assert_eq!(
+            guess("the_show_s10e125.mkv"),
+            Some(("the show".to_owned(), 10, 125))
+        );
+        assert_eq!(
+            guess("The Show - S02 E03.mkv"),
+            Some(("The Show".to_owned(), 2, 3))
+        );
+    }
+
+    #[test]
+    fn guess_episode_reads_numbers_with_an_x() {
+        assert_eq!(
+            guess("Show Name 1x02.mkv"),
+            Some(("Show Name".to_owned(), 1, 2))
+        );
+    }
+
+    #[test]
+    fn guess_episode_leaves_the_series_empty_without_one() {
+        assert_eq!(guess("S03E04.mkv"), Some((String::new(), 3, 4)));
+    }
+
+    #[test]
+    fn guess_episode_ignores_names_without_episodes() {
+        assert_eq!(guess("Holiday Video.mkv"), None);
+        assert_eq!(guess("Show 1920x1080.mkv"), None);
+        assert_eq!(guess("Show 1080p.mkv"), None);
+    }
+}
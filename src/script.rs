@@ -0,0 +1,211 @@
+use crate::database::*;
+use crate::files::TransferMode;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Quotes a string so that a POSIX shell reads it back as a single word, no matter which characters it contains.
+///
+/// The string is wrapped in single quotes and every single quote inside of it is replaced with `'\''`, which closes the quotes, adds an escaped quote and opens them again.
+///
+/// # Example
+/// ```
+/// assert_eq!(shell_quote("Bob's Show"), "'Bob'\\''s Show'");
+/// ```
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Returns the path of the undo script written along with the given script, which is the same file name with `-undo` added before the extension.
+///
+/// # Example
+/// ```
+/// assert_eq!(undo_script_path(Path::new("rename.sh")), PathBuf::from("rename-undo.sh"));
+/// ```
+pub fn undo_script_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}-undo.{}", extension.to_string_lossy()),
+        None => format!("{stem}-undo"),
+    };
+    path.with_file_name(name)
+}
+
+/// Returns a POSIX sh script that transfers every given episode to its new_path the same way [`crate::files::move_files()`] would with the given [`TransferMode`].
+///
+/// The script stops at the first command that fails. Before each file is transferred, its folder is created with `mkdir -p`, and the script stops if its new_path already exists so that no file is overwritten.
+/// Episodes whose old_path is already their new_path are skipped. Copies keep the times and mode of the original with `cp -p`.
+/// Symbolic links point to the absolute path of the old_path, as long as it still exists when the script is generated.
+///
+/// # Example
+/// ```
+/// let script = rename_script(&select_plan(&db).await?, TransferMode::Copy);
+/// ```
+pub fn rename_script(episodes: &[Episode], mode: TransferMode) -> String {
+    let mut script = script_header("Transfers the files to their new path.");
+    script += "# Stops the script instead of overwriting a file that already exists.\n";
+    script += "ensure_absent() {\n    if [ -e \"$1\" ] || [ -L \"$1\" ]; then\n        echo \"$1 already exists.\" >&2\n        exit 1\n    fi\n}\n\n";
+    let mut created = HashSet::new();
+    for episode in episodes.iter().filter(|x| x.old_path != x.new_path) {
+        if let Some(parent) = Path::new(&episode.new_path).parent() {
+            if created.insert(parent.to_path_buf()) {
+                script += &format!("mkdir -p -- {}\n", shell_quote(&parent.to_string_lossy()));
+            }
+        }
+        let old_path = shell_quote(&episode.old_path);
+        let new_path = shell_quote(&episode.new_path);
+        script += &format!("ensure_absent {new_path}\n");
+        script += &match mode {
+            TransferMode::Move => format!("mv -- {old_path} {new_path}\n"),
+            TransferMode::Copy => format!("cp -p -- {old_path} {new_path}\n"),
+            TransferMode::Hardlink => format!("ln -- {old_path} {new_path}\n"),
+            TransferMode::Symlink => {
+                let target = std::fs::canonicalize(&episode.old_path)
+                    .unwrap_or_else(|_| PathBuf::from(&episode.old_path));
+                format!(
+                    "ln -s -- {} {new_path}\n",
+                    shell_quote(&target.to_string_lossy())
+                )
+            }
+        };
+    }
+    script
+}
+
+/// Returns a POSIX sh script that reverts what [`rename_script()`] does for the same episodes and [`TransferMode`].
+///
+/// The episodes are reverted in reverse order. Moved files are moved back to their old_path, while copies and links are removed from their new_path.
+/// Episodes whose old_path is already their new_path are skipped, since [`rename_script()`] leaves them alone.
+/// Afterwards, the season and series folders of the new paths are removed with `rmdir` if they are left empty, so folders that held other files are kept.
+///
+/// # Example
+/// ```
+/// let undo = undo_script(&select_plan(&db).await?, TransferMode::Move);
+/// ```
+pub fn undo_script(episodes: &[Episode], mode: TransferMode) -> String {
+    let mut script = script_header("Reverts the files to their old path.");
+    let mut folders = Vec::new();
+    for episode in episodes.iter().rev().filter(|x| x.old_path != x.new_path) {
+        let old_path = shell_quote(&episode.old_path);
+        let new_path = shell_quote(&episode.new_path);
+        script += &match mode {
+            TransferMode::Move => format!("mv -- {new_path} {old_path}\n"),
+            _ => format!("rm -f -- {new_path}\n"),
+        };
+        for folder in Path::new(&episode.new_path).ancestors().skip(1).take(2) {
+            if !folders.contains(&folder.to_path_buf()) {
+                folders.push(folder.to_path_buf());
+            }
+        }
+    }
+    folders.sort_by_key(|x| std::cmp::Reverse(x.components().count()));
+    for folder in folders {
+        script += &format!(
+            "rmdir -- {} 2>/dev/null || true\n",
+            shell_quote(&folder.to_string_lossy())
+        );
+    }
+    script
+}
+
+/// Returns the first lines shared by every script, made of the shebang, a comment with the given description and `set -e` so the script stops at the first error.
+fn script_header(description: &str) -> String {
+    format!("#!/bin/sh\n# Generated by rplexfmtr. {description}\n# Review the commands below before running this script.\nset -e\n\n")
+}
+
+/// Writes the [`rename_script()`] for every episode entry of the database to the given path, along with the [`undo_script()`] at the path given by [`undo_script_path()`], returning the path of the undo script.
+///
+/// Both scripts are made executable on Unix. Existing files are overwritten.
+///
+/// # Example
+/// ```
+/// let undo_path = emit_scripts(&db, Path::new("rename.sh"), TransferMode::Move).await?;
+/// ```
+pub async fn emit_scripts(
+    db: &sqlx::SqlitePool,
+    path: &Path,
+    mode: TransferMode,
+) -> anyhow::Result<PathBuf> {
+    let episodes = select_plan(db).await?;
+    let undo_path = undo_script_path(path);
+    for (path, script) in [
+        (path, rename_script(&episodes, mode)),
+        (undo_path.as_path(), undo_script(&episodes, mode)),
+    ] {
+        std::fs::write(path, script)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+        }
+    }
+    Ok(undo_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_quote_wraps_in_single_quotes() {
+        assert_eq!(shell_quote("Show"), "'Show'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("$HOME `id` \"a b\""), "'$HOME `id` \"a b\"'");
+    }
+
+    #[test]
+    fn shell_quote_escapes_single_quotes() {
+        assert_eq!(shell_quote("Bob's Show"), "'Bob'\\''s Show'");
+        assert_eq!(shell_quote("''"), "''\\'''\\'''");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shell_quote_is_read_back_by_sh() {
+        let value = "Bob's \"Show\" $HOME `id` \\ * ;\nnext";
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf %s {}", shell_quote(value)))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), value);
+    }
+
+    #[test]
+    fn undo_script_path_adds_undo_before_the_extension() {
+        assert_eq!(
+            undo_script_path(Path::new("rename.sh")),
+            PathBuf::from("rename-undo.sh")
+        );
+        assert_eq!(
+            undo_script_path(Path::new("/tmp/rename")),
+            PathBuf::from("/tmp/rename-undo")
+        );
+    }
+
+    fn episode(old_path: &str, new_path: &str) -> Episode {
+        Episode {
+            series_name: "Show".to_owned(),
+            season: 1,
+            episode: 1,
+            title: None,
+            old_path: old_path.to_owned(),
+            new_path: new_path.to_owned(),
+        }
+    }
+
+    #[test]
+    fn scripts_skip_episodes_already_at_their_new_path() {
+        let episodes = [
+            episode("/in/a.mkv", "/out/Show/Season 1/a.mkv"),
+            episode("/out/Show/Season 1/b.mkv", "/out/Show/Season 1/b.mkv"),
+        ];
+        let script = rename_script(&episodes, TransferMode::Copy);
+        assert!(script.contains(
+            "ensure_absent '/out/Show/Season 1/a.mkv'\ncp -p -- '/in/a.mkv' '/out/Show/Season 1/a.mkv'\n"
+        ));
+        assert!(!script.contains("b.mkv"));
+        let undo = undo_script(&episodes, TransferMode::Move);
+        assert!(undo.contains("mv -- '/out/Show/Season 1/a.mkv' '/in/a.mkv'\n"));
+        assert!(!undo.contains("b.mkv"));
+    }
+}
@@ -0,0 +1,272 @@
+use crate::validate::valid_name;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The episode titles of an episode list, read by [`parse_titles()`] from a CSV or TXT file or from lines pasted at the titles prompt.
+///
+/// Titles are kept by season and episode. Titles given without a season, such as the ones of a TXT list, are used for the episode of any season.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Titles {
+    /// The titles by season, if given, and episode, already sanitized with [`sanitize_title()`].
+    pub entries: HashMap<(Option<u32>, u32), String>,
+}
+
+impl Titles {
+    /// Returns the title of the given episode, preferring a title given for its season over one given without a season.
+    ///
+    /// # Example
+    /// ```
+    /// let titles = parse_titles("Pilot\nThe Second One", false)?;
+    /// assert_eq!(titles.get(1, 2), Some("The Second One"));
+    /// ```
+    pub fn get(&self, season: u32, episode: u32) -> Option<&str> {
+        self.entries
+            .get(&(Some(season), episode))
+            .or_else(|| self.entries.get(&(None, episode)))
+            .map(|x| x.as_str())
+    }
+
+    /// The amount of titles of the list.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// True if the list has no titles.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Turns an episode title into one that can be part of a file name, returning [`None`] if it is still not accepted by [`valid_name()`].
+///
+/// Colons are replaced with ` -`, slashes and backslashes with `-`, the other characters that are not valid in a file name are removed, runs of whitespace are collapsed and trailing dots and spaces are removed.
+///
+/// # Example
+/// ```
+/// assert_eq!(sanitize_title("Who Are You?: Part 1/2"), Some("Who Are You - Part 1-2".to_owned()));
+/// ```
+pub fn sanitize_title(title: &str) -> Option<String> {
+    let title = title
+        .replace(':', " -")
+        .replace(['/', '\\'], "-")
+        .replace(['<', '>', '"', '|', '?', '*', '\0'], "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let title = title.trim_end_matches(['.', ' ']);
+    match valid_name(title) {
+        true => Some(title.to_owned()),
+        false => None,
+    }
+}
+
+/// Parses an episode list into [`Titles`].
+///
+/// A TXT list has one title per line, the first line being the title of episode 1. Empty lines are ignored.
+/// A CSV list has `episode,title` or `season,episode,title` rows, with an optional header row. Fields holding commas can be quoted.
+///
+/// Every title is sanitized with [`sanitize_title()`], and titles left invalid are ignored.
+///
+/// # Errors
+/// If a CSV row cannot be read, does not have two or three fields, or has a season or episode that is not a number.
+///
+/// # Example
+/// ```
+/// let titles = parse_titles("season,episode,title\n1,1,Pilot\n1,2,\"Hello, World\"", true)?;
+/// assert_eq!(titles.get(1, 2), Some("Hello, World"));
+/// ```
+pub fn parse_titles(text: &str, csv: bool) -> Result<Titles, String> {
+    let mut titles = Titles::default();
+    if !csv {
+        for (index, line) in text.lines().filter(|x| !x.trim().is_empty()).enumerate() {
+            if let Some(title) = sanitize_title(line) {
+                titles.entries.insert((None, index as u32 + 1), title);
+            }
+        }
+        return Ok(titles);
+    }
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| e.to_string())?;
+        let line = record.position().map(|x| x.line()).unwrap_or(0);
+        let (season, episode, title) = match record.len() {
+            2 => (None, &record[0], &record[1]),
+            3 => (Some(&record[0]), &record[1], &record[2]),
+            _ => {
+                return Err(format!(
+                    "line {line} has {} fields instead of episode,title or season,episode,title",
+                    record.len()
+                ))
+            }
+        };
+        let numbers = (
+            season.map(|x| x.parse::<u32>()).transpose(),
+            episode.parse::<u32>(),
+        );
+        let (season, episode) = match numbers {
+            (Ok(season), Ok(episode)) => (season, episode),
+            _ if index == 0 => continue,
+            _ => {
+                return Err(format!(
+                    "line {line} has a season or episode that is not a number"
+                ))
+            }
+        };
+        if let Some(title) = sanitize_title(title) {
+            titles.entries.insert((season, episode), title);
+        }
+    }
+    Ok(titles)
+}
+
+/// Reads an episode list file with [`parse_titles()`], as CSV if its extension is `csv` and as TXT otherwise.
+///
+/// # Errors
+/// If the file cannot be read or is not a valid episode list.
+///
+/// # Example
+/// ```
+/// let titles = load_titles(Path::new("/home/user/show/season1.csv"))?;
+/// ```
+pub fn load_titles(path: &Path) -> anyhow::Result<Titles> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Could not read {}: {e}", path.display()))?;
+    let csv = path
+        .extension()
+        .is_some_and(|x| x.eq_ignore_ascii_case("csv"));
+    parse_titles(&text, csv)
+        .map_err(|e| anyhow::anyhow!("Invalid episode list {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles(entries: &[(Option<u32>, u32, &str)]) -> Titles {
+        Titles {
+            entries: entries
+                .iter()
+                .map(|(season, episode, title)| ((*season, *episode), title.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn sanitize_title_replaces_invalid_characters() {
+        assert_eq!(
+            sanitize_title("Who Are You?: Part 1/2"),
+            Some("Who Are You - Part 1-2".to_owned())
+        );
+        assert_eq!(
+            sanitize_title(r#"<The> "Back\Slash" | Star*"#),
+            Some("The Back-Slash Star".to_owned())
+        );
+    }
+
+    #[test]
+    fn sanitize_title_collapses_whitespace_and_trims_the_end() {
+        assert_eq!(
+            sanitize_title("  The \t Long   Night  "),
+            Some("The Long Night".to_owned())
+        );
+        assert_eq!(
+            sanitize_title("To Be Continued..."),
+            Some("To Be Continued".to_owned())
+        );
+        assert_eq!(sanitize_title("The End. "), Some("The End".to_owned()));
+    }
+
+    #[test]
+    fn sanitize_title_rejects_titles_left_invalid() {
+        assert_eq!(sanitize_title(""), None);
+        assert_eq!(sanitize_title("???"), None);
+        assert_eq!(sanitize_title("..."), None);
+        assert_eq!(sanitize_title("NUL"), None);
+        assert_eq!(sanitize_title("Port COM1"), None);
+    }
+
+    #[test]
+    fn parse_titles_reads_txt_lists() {
+        assert_eq!(
+            parse_titles(
+                "Pilot\n\n  \nThe Second One\r\nPart 1/2\n???\nThe End\n",
+                false
+            ),
+            Ok(titles(&[
+                (None, 1, "Pilot"),
+                (None, 2, "The Second One"),
+                (None, 3, "Part 1-2"),
+                (None, 5, "The End"),
+            ]))
+        );
+        assert_eq!(parse_titles("", false), Ok(Titles::default()));
+    }
+
+    #[test]
+    fn parse_titles_reads_csv_lists() {
+        assert_eq!(
+            parse_titles("1,Pilot\n2,\"Hello, World\"\n3, Who: Me? ", true),
+            Ok(titles(&[
+                (None, 1, "Pilot"),
+                (None, 2, "Hello, World"),
+                (None, 3, "Who - Me"),
+            ]))
+        );
+        assert_eq!(
+            parse_titles("1,1,Pilot\n2,1,Return", true),
+            Ok(titles(&[(Some(1), 1, "Pilot"), (Some(2), 1, "Return")]))
+        );
+    }
+
+    #[test]
+    fn parse_titles_skips_csv_headers() {
+        assert_eq!(
+            parse_titles(
+                "season,episode,title\n1,1,Pilot\n1,2,\"Hello, World\"",
+                true
+            ),
+            Ok(titles(&[
+                (Some(1), 1, "Pilot"),
+                (Some(1), 2, "Hello, World")
+            ]))
+        );
+        assert_eq!(
+            parse_titles("Episode,Title\n1,Pilot", true),
+            Ok(titles(&[(None, 1, "Pilot")]))
+        );
+    }
+
+    #[test]
+    fn parse_titles_rejects_invalid_csv_rows() {
+        assert_eq!(
+            parse_titles("1,Pilot\n2", true),
+            Err("line 2 has 1 fields instead of episode,title or season,episode,title".to_owned())
+        );
+        assert_eq!(
+            parse_titles("1,1,Pilot,Extra", true),
+            Err("line 1 has 4 fields instead of episode,title or season,episode,title".to_owned())
+        );
+        assert_eq!(
+            parse_titles("episode,title\n1,Pilot\ntwo,Return", true),
+            Err("line 3 has a season or episode that is not a number".to_owned())
+        );
+        assert_eq!(parse_titles("1,-1,Pilot", true), Ok(Titles::default()));
+    }
+
+    #[test]
+    fn get_prefers_titles_of_the_season() {
+        let titles = titles(&[
+            (None, 1, "Any Pilot"),
+            (Some(2), 1, "Second Pilot"),
+            (None, 2, "Any Second"),
+        ]);
+        assert_eq!(titles.get(1, 1), Some("Any Pilot"));
+        assert_eq!(titles.get(2, 1), Some("Second Pilot"));
+        assert_eq!(titles.get(2, 2), Some("Any Second"));
+        assert_eq!(titles.get(1, 3), None);
+    }
+}
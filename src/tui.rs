@@ -0,0 +1,680 @@
+use crate::cleanup::{cleanup_dirs, plan_cleanup};
+use crate::database::*;
+use crate::files::*;
+use crate::library::{route_libraries, Library};
+use crate::picker::Picker;
+use crate::preflight::preflight;
+use crate::rules::match_directory;
+use crate::titles::{load_titles, Titles};
+use crate::validate::*;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{
+    Block, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, TableState,
+};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashSet;
+use std::fs::DirEntry;
+use std::path::{Path, PathBuf};
+
+/// A selected file along with the season, episode and title it will be renamed to.
+type Numbered<'a> = (&'a DirEntry, u32, u32, Option<String>);
+
+/// The answers given in the terminal user interface for one of the input directories.
+struct Directory {
+    path: PathBuf,
+    library: Option<usize>,
+    files: Vec<DirEntry>,
+    name: String,
+    season: String,
+    titles_path: String,
+    titles: Option<Titles>,
+    picker: Picker,
+}
+
+impl Directory {
+    /// Returns the library, series name and selected files along with their season, episode and title, once all the answers for the directory are valid.
+    ///
+    /// The files are numbered in the order given, or with the episodes parsed by the [`crate::mapping::Mapping`] typed as the filter of the file list, in which case the season is only needed for the files it does not give one.
+    /// The titles are taken from the episode list, else from the titles parsed by the mapping.
+    fn answers(&self, order: SelectionOrder) -> Option<(usize, &str, Vec<Numbered<'_>>)> {
+        let library = self.library?;
+        let season = self.season.parse::<u32>().ok();
+        let selection = self.picker.selection();
+        if !valid_name(&self.name)
+            || selection.indexes.is_empty()
+            || (!self.titles_path.is_empty() && self.titles.is_none())
+        {
+            return None;
+        }
+        let title = |season: u32, episode: u32| {
+            self.titles
+                .as_ref()
+                .and_then(|x| x.get(season, episode))
+                .map(|x| x.to_owned())
+        };
+        let files = match selection.parsed.is_empty() {
+            true => {
+                let season = season?;
+                let mut indexes = selection.indexes;
+                if order == SelectionOrder::Natural {
+                    indexes.sort();
+                }
+                indexes
+                    .into_iter()
+                    .enumerate()
+                    .map(|(episode, index)| {
+                        let episode = (episode as u32) + 1;
+                        (&self.files[index], season, episode, title(season, episode))
+                    })
+                    .collect()
+            }
+            false => selection
+                .indexes
+                .into_iter()
+                .zip(selection.parsed)
+                .map(|(index, parsed)| {
+                    let season = parsed.season.or(season)?;
+                    Some((
+                        &self.files[index],
+                        season,
+                        parsed.episode,
+                        title(season, parsed.episode).or(parsed.title),
+                    ))
+                })
+                .collect::<Option<Vec<_>>>()?,
+        };
+        Some((library, &self.name, files))
+    }
+}
+
+/// The pane of the terminal user interface that receives key presses.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Directories,
+    Name,
+    Season,
+    Titles,
+    Files,
+    Preview,
+}
+
+impl Focus {
+    /// The next pane, skipping the episode list unless the --titles flag was given.
+    fn next(self, titles: bool) -> Self {
+        match self {
+            Focus::Directories => Focus::Name,
+            Focus::Name => Focus::Season,
+            Focus::Season if titles => Focus::Titles,
+            Focus::Season | Focus::Titles => Focus::Files,
+            Focus::Files => Focus::Preview,
+            Focus::Preview => Focus::Directories,
+        }
+    }
+
+    /// The previous pane, skipping the episode list unless the --titles flag was given.
+    fn previous(self, titles: bool) -> Self {
+        match self {
+            Focus::Directories => Focus::Preview,
+            Focus::Name => Focus::Directories,
+            Focus::Season => Focus::Name,
+            Focus::Titles => Focus::Season,
+            Focus::Files if titles => Focus::Titles,
+            Focus::Files => Focus::Season,
+            Focus::Preview => Focus::Files,
+        }
+    }
+}
+
+/// A rename shown in the preview, along with why it cannot be executed if it conflicts with another rename or an existing file.
+struct Planned {
+    episode: Episode,
+    strategy: Strategy,
+    conflict: Option<&'static str>,
+}
+
+/// State of the terminal user interface started by [`run_tui()`].
+struct App {
+    directories: Vec<Directory>,
+    current: ListState,
+    focus: Focus,
+    planned: Vec<Planned>,
+    preview: TableState,
+    status: String,
+    confirm: bool,
+    matched: Vec<Episode>,
+}
+
+impl App {
+    fn new(args: &Cli) -> anyhow::Result<Self> {
+        let mut directories = Vec::new();
+        let mut matched = Vec::new();
+        let mut skipped = 0;
+        for (path, library) in args.input_paths.iter().zip(route_libraries(args)?) {
+            let (episodes, files, conflicts) = match_directory(args, path, library, &matched)?;
+            matched.extend(episodes);
+            skipped += conflicts.len();
+            let picker = Picker::new(get_file_names(&files)?);
+            directories.push(Directory {
+                path: path.to_owned(),
+                library,
+                files,
+                name: String::new(),
+                season: String::new(),
+                titles_path: String::new(),
+                titles: None,
+                picker,
+            });
+        }
+        Ok(App {
+            directories,
+            current: ListState::default().with_selected(Some(0)),
+            focus: Focus::Name,
+            planned: Vec::new(),
+            preview: TableState::default(),
+            status: match skipped {
+                0 => String::new(),
+                _ => format!(
+                    "{skipped} files matched by the rules have the same target as another file and are left to choose."
+                ),
+            },
+            confirm: false,
+            matched,
+        })
+    }
+
+    fn directory(&mut self) -> &mut Directory {
+        &mut self.directories[self.current.selected().unwrap_or(0)]
+    }
+
+    fn conflicts(&self) -> usize {
+        self.planned.iter().filter(|x| x.conflict.is_some()).count()
+    }
+
+    /// Rebuilds the episodes in the database from the current answers and recomputes the preview and its conflicts.
+    ///
+    /// The episodes matched by the rules are inserted first. Renames whose target is already used by another rename, or whose file was already chosen in another directory, are not inserted into the database and are only shown in the preview as conflicts.
+    async fn refresh(&mut self, db: &sqlx::SqlitePool, args: &Cli) -> anyhow::Result<()> {
+        delete_all_episodes(db).await?;
+        let mut sources = HashSet::new();
+        let mut targets = HashSet::new();
+        let mut rejected = Vec::new();
+        for episode in &self.matched {
+            sources.insert(episode.old_path.clone());
+            targets.insert(episode.new_path.clone());
+            insert_episode(
+                db,
+                &episode.series_name,
+                episode.season,
+                episode.episode,
+                episode.title.as_deref(),
+                PathBuf::from(&episode.old_path),
+                PathBuf::from(&episode.new_path),
+            )
+            .await?;
+        }
+        for directory in &self.directories {
+            let Some((library, name, files)) = directory.answers(args.order) else {
+                continue;
+            };
+            let library = &args.libraries[library];
+            for (file, season, episode, title) in files {
+                let episode = Episode {
+                    series_name: name.to_owned(),
+                    season,
+                    episode,
+                    old_path: file.path().to_str().unwrap().to_owned(),
+                    new_path: episode_path(
+                        library,
+                        name,
+                        season,
+                        episode,
+                        title.as_deref(),
+                        &file.path(),
+                    )
+                    .to_str()
+                    .unwrap()
+                    .to_owned(),
+                    title,
+                };
+                let strategy = strategy(
+                    Path::new(&episode.old_path),
+                    Path::new(&episode.new_path),
+                    args.transfer_mode(),
+                );
+                if !sources.insert(episode.old_path.clone()) {
+                    rejected.push(Planned {
+                        episode,
+                        strategy,
+                        conflict: Some("file chosen twice"),
+                    });
+                } else if !targets.insert(episode.new_path.clone()) {
+                    rejected.push(Planned {
+                        episode,
+                        strategy,
+                        conflict: Some("duplicate target"),
+                    });
+                } else {
+                    insert_episode(
+                        db,
+                        &episode.series_name,
+                        episode.season,
+                        episode.episode,
+                        episode.title.as_deref(),
+                        file.path(),
+                        PathBuf::from(&episode.new_path),
+                    )
+                    .await?;
+                }
+            }
+        }
+        self.planned = select_plan(db)
+            .await?
+            .into_iter()
+            .map(|episode| {
+                let conflict = match Path::new(&episode.new_path).exists() {
+                    true => Some("target already exists"),
+                    false => None,
+                };
+                Planned {
+                    strategy: strategy(
+                        Path::new(&episode.old_path),
+                        Path::new(&episode.new_path),
+                        args.transfer_mode(),
+                    ),
+                    episode,
+                    conflict,
+                }
+            })
+            .collect();
+        self.planned.append(&mut rejected);
+        if self.preview.selected().unwrap_or(0) >= self.planned.len() {
+            self.preview.select(None);
+        }
+        Ok(())
+    }
+
+    /// Handles a key press, returning [`Some`] with whether the changes should be executed once the user is done.
+    async fn handle_key(
+        &mut self,
+        key: KeyEvent,
+        db: &sqlx::SqlitePool,
+        args: &Cli,
+    ) -> anyhow::Result<Option<bool>> {
+        if self.confirm {
+            self.confirm = false;
+            self.status.clear();
+            return Ok(match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => Some(true),
+                _ => None,
+            });
+        }
+        self.status.clear();
+        let mut edited = false;
+        match key.code {
+            KeyCode::Esc => return Ok(Some(false)),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(Some(false))
+            }
+            KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if self.planned.is_empty() {
+                    self.status = "There is nothing to rename yet.".to_owned();
+                } else if self.conflicts() > 0 {
+                    self.status = "Resolve the conflicts before executing.".to_owned();
+                } else if let Some(problem) = preflight(
+                    &self
+                        .planned
+                        .iter()
+                        .map(|x| x.episode.clone())
+                        .collect::<Vec<_>>(),
+                    args.transfer_mode(),
+                )
+                .problems
+                .first()
+                {
+                    self.status = format!("Cannot rename the files, {problem}");
+                } else {
+                    self.confirm = true;
+                    let removed = match args.cleanup {
+                        true => {
+                            let episodes = select_plan(db).await?;
+                            let dirs =
+                                plan_cleanup(&cleanup_dirs(args, &episodes), &episodes, args)
+                                    .into_iter()
+                                    .map(|x| x.dir.display().to_string())
+                                    .collect::<Vec<_>>();
+                            match dirs.is_empty() {
+                                true => String::new(),
+                                false => format!(" and remove {}", dirs.join(", ")),
+                            }
+                        }
+                        false => String::new(),
+                    };
+                    self.status = format!(
+                        "Press y to rename {} files{removed}, any other key to cancel.",
+                        self.planned.len()
+                    );
+                }
+            }
+            KeyCode::Tab => self.focus = self.focus.next(args.titles),
+            KeyCode::BackTab => self.focus = self.focus.previous(args.titles),
+            _ => match self.focus {
+                Focus::Directories => match key.code {
+                    KeyCode::Up => self.current.select_previous(),
+                    KeyCode::Down => self.current.select(Some(
+                        (self.current.selected().unwrap_or(0) + 1).min(self.directories.len() - 1),
+                    )),
+                    KeyCode::Enter => self.focus = Focus::Name,
+                    KeyCode::Left | KeyCode::Right if !args.libraries.is_empty() => {
+                        let amount = args.libraries.len();
+                        let directory = self.directory();
+                        directory.library = Some(match (directory.library, key.code) {
+                            (None, _) => 0,
+                            (Some(library), KeyCode::Left) => (library + amount - 1) % amount,
+                            (Some(library), _) => (library + 1) % amount,
+                        });
+                        edited = true;
+                    }
+                    _ => {}
+                },
+                Focus::Name => match key.code {
+                    KeyCode::Backspace => edited = self.directory().name.pop().is_some(),
+                    KeyCode::Enter => self.focus = Focus::Season,
+                    KeyCode::Char(c) => {
+                        self.directory().name.push(c);
+                        edited = true;
+                    }
+                    _ => {}
+                },
+                Focus::Season => match key.code {
+                    KeyCode::Backspace => edited = self.directory().season.pop().is_some(),
+                    KeyCode::Enter => self.focus = Focus::Season.next(args.titles),
+                    KeyCode::Char(c) if c.is_ascii_digit() => {
+                        self.directory().season.push(c);
+                        edited = true;
+                    }
+                    _ => {}
+                },
+                Focus::Titles => {
+                    let directory = self.directory();
+                    match key.code {
+                        KeyCode::Backspace => edited = directory.titles_path.pop().is_some(),
+                        KeyCode::Enter => self.focus = Focus::Files,
+                        KeyCode::Char(c) => {
+                            directory.titles_path.push(c);
+                            edited = true;
+                        }
+                        _ => {}
+                    }
+                    if edited {
+                        let directory = self.directory();
+                        directory.titles = load_titles(Path::new(&directory.titles_path)).ok();
+                    }
+                }
+                Focus::Files => {
+                    edited = self.directory().picker.handle_key(key);
+                }
+                Focus::Preview => match key.code {
+                    KeyCode::Up => self.preview.select_previous(),
+                    KeyCode::Down => self.preview.select_next(),
+                    _ => {}
+                },
+            },
+        }
+        if edited {
+            self.refresh(db, args).await?;
+        }
+        Ok(None)
+    }
+
+    fn border(&self, focus: Focus, valid: bool) -> Style {
+        match (self.focus == focus, valid) {
+            (_, false) => Style::default().fg(Color::Red),
+            (true, true) => Style::default().fg(Color::Green),
+            (false, true) => Style::default(),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame, args: &Cli) {
+        let [top, preview_area, help_area] = Layout::vertical([
+            Constraint::Percentage(50),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [directories_area, answers_area] =
+            Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)]).areas(top);
+        let [name_area, season_area, titles_area, files_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(match args.titles {
+                true => 3,
+                false => 0,
+            }),
+            Constraint::Min(3),
+        ])
+        .areas(answers_area);
+        self.draw_directories(frame, directories_area, &args.libraries);
+        self.draw_answers(frame, [name_area, season_area, titles_area, files_area]);
+        self.draw_preview(frame, preview_area);
+        let help = match self.status.is_empty() {
+            true => Line::from(
+                "Tab: next pane | Space: toggle file | Shift+Up/Down: select range | Ctrl+X: execute | Esc: quit",
+            )
+            .dark_gray(),
+            false => Line::from(self.status.as_str()).yellow(),
+        };
+        frame.render_widget(Paragraph::new(help), help_area);
+    }
+
+    fn draw_directories(&mut self, frame: &mut Frame, area: Rect, libraries: &[Library]) {
+        let items = self
+            .directories
+            .iter()
+            .map(|directory| {
+                let mark = match directory.answers(SelectionOrder::Natural).is_some() {
+                    true => "✓ ".green(),
+                    false => "  ".into(),
+                };
+                let library = match directory.library.map(|x| &libraries[x]) {
+                    _ if libraries.len() < 2 => "".into(),
+                    Some(library) => {
+                        format!(" → {}", library.name.as_deref().unwrap_or("output")).blue()
+                    }
+                    None => " → choose a library with ←/→".red(),
+                };
+                ListItem::new(Line::from(vec![
+                    mark,
+                    directory.path.to_str().unwrap().to_owned().into(),
+                    library,
+                ]))
+            })
+            .collect::<Vec<_>>();
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(" Directories ")
+                        .border_style(self.border(Focus::Directories, true)),
+                )
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            area,
+            &mut self.current,
+        );
+    }
+
+    fn draw_answers(&mut self, frame: &mut Frame, areas: [Rect; 4]) {
+        let [name_area, season_area, titles_area, files_area] = areas;
+        let focus = self.focus;
+        let (name_valid, titles_valid) = {
+            let directory = self.directory();
+            (
+                directory.name.is_empty() || valid_name(&directory.name),
+                directory.titles_path.is_empty() || directory.titles.is_some(),
+            )
+        };
+        let name_style = self.border(Focus::Name, name_valid);
+        let season_style = self.border(Focus::Season, true);
+        let titles_style = self.border(Focus::Titles, titles_valid);
+        let files_border = match focus == Focus::Files {
+            true => Color::Green,
+            false => Color::Reset,
+        };
+        let directory = self.directory();
+        frame.render_widget(
+            Paragraph::new(directory.name.as_str()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Series name ")
+                    .border_style(name_style),
+            ),
+            name_area,
+        );
+        frame.render_widget(
+            Paragraph::new(directory.season.as_str()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Season ")
+                    .border_style(season_style),
+            ),
+            season_area,
+        );
+        let titles_title = match &directory.titles {
+            Some(titles) => format!(" Episode list ({} titles) ", titles.len()),
+            None => " Episode list (CSV or TXT file) ".to_owned(),
+        };
+        frame.render_widget(
+            Paragraph::new(directory.titles_path.as_str()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(titles_title)
+                    .border_style(titles_style),
+            ),
+            titles_area,
+        );
+        let title = match directory.picker.filter.is_empty() {
+            true => "Files".to_owned(),
+            false => format!("Files matching \"{}\"", directory.picker.filter),
+        };
+        directory
+            .picker
+            .render_list(frame, files_area, &title, files_border);
+    }
+
+    fn draw_preview(&mut self, frame: &mut Frame, area: Rect) {
+        let rows = self
+            .planned
+            .iter()
+            .map(|planned| {
+                Row::new(vec![
+                    Cell::from(planned.episode.series_name.clone()).light_red(),
+                    Cell::from(planned.episode.season.to_string()).yellow(),
+                    Cell::from(planned.episode.episode.to_string()).cyan(),
+                    Cell::from(planned.episode.title.clone().unwrap_or_default()),
+                    Cell::from(planned.episode.old_path.clone()).light_blue(),
+                    Cell::from(planned.episode.new_path.clone()).light_green(),
+                    Cell::from(planned.strategy.to_string()).magenta(),
+                    Cell::from(planned.conflict.unwrap_or_default()).red(),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let title = format!(
+            " Preview ({} renames, {} conflicts) ",
+            self.planned.len() - self.conflicts(),
+            self.conflicts()
+        );
+        frame.render_stateful_widget(
+            Table::new(
+                rows,
+                [
+                    Constraint::Max(20),
+                    Constraint::Length(6),
+                    Constraint::Length(7),
+                    Constraint::Max(20),
+                    Constraint::Fill(1),
+                    Constraint::Fill(1),
+                    Constraint::Length(13),
+                    Constraint::Length(21),
+                ],
+            )
+            .header(
+                Row::new(vec![
+                    "series_name",
+                    "season",
+                    "episode",
+                    "title",
+                    "old_path",
+                    "new_path",
+                    "strategy",
+                    "conflict",
+                ])
+                .bold(),
+            )
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .border_style(self.border(Focus::Preview, self.conflicts() == 0)),
+            )
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            area,
+            &mut self.preview,
+        );
+    }
+
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        db: &sqlx::SqlitePool,
+        args: &Cli,
+    ) -> anyhow::Result<bool> {
+        loop {
+            terminal.draw(|frame| self.draw(frame, args))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if let Some(execute) = self.handle_key(key, db, args).await? {
+                    return Ok(execute);
+                }
+            }
+        }
+    }
+}
+
+/// Runs the whole renaming workflow in a single terminal user interface instead of the sequential prompts of [`crate::main()`].
+///
+/// The screen is split into the following panes, which are cycled through with Tab and Shift+Tab:
+/// 1. Directories: The input directories given with the -i flag. A check mark is shown next to the directories whose answers are complete. When there is more than one [`Library`], the library of each directory, the one chosen with the --library flag if any, is shown next to it and can be changed with the Left and Right arrows.
+/// 2. Series name: The name of the series for the current directory, shown in red if it is not accepted by [`valid_name()`].
+/// 3. Season: The season number for the current directory.
+/// 4. Episode list: The path of a CSV or TXT episode list giving the titles of the episodes of the current directory, read with [`load_titles()`] and shown in red if it cannot be read. Only shown when the --titles flag was given.
+/// 5. Files: The files of the current directory that were not matched by the rules with [`match_directory()`], chosen in the same way as in [`crate::picker::pick_files()`]. The files matched by the rules are always part of the preview. Typing `re:` followed by a regular expression numbers the files with the episodes it parses, as described in [`crate::mapping::Mapping`].
+/// 6. Preview: The renames that will be performed, in the same order as [`preview_changes()`], along with any conflicts.
+///
+/// Every time an answer is edited, the episodes in the database are rebuilt with [`delete_all_episodes()`] and [`insert_episode()`] and the preview is updated.
+/// A rename conflicts if its target path already exists, is the target of another rename, or if its file was chosen in two directories.
+/// Renames whose target or file is already used by another rename are not inserted into the database, while renames whose target already exists are inserted and only flagged. Either way, the changes cannot be executed until every conflict is resolved.
+///
+/// Pressing Ctrl+X asks for confirmation and returns true if there are no conflicts and [`crate::preflight::preflight()`] finds no problems, leaving the database ready for [`move_files()`]. Pressing Esc returns false.
+///
+/// # Panics
+/// If any of the paths cannot be unwrapped to a [`&str`].
+///
+/// # Example
+/// ```
+/// if run_tui(&db, &args).await? {
+///     move_files(&db, &args).await?;
+/// }
+/// ```
+pub async fn run_tui(db: &sqlx::SqlitePool, args: &Cli) -> anyhow::Result<bool> {
+    let mut app = App::new(args)?;
+    app.refresh(db, args).await?;
+    let mut terminal = ratatui::try_init()?;
+    let result = app.run(&mut terminal, db, args).await;
+    ratatui::try_restore()?;
+    result
+}
@@ -1,3 +1,4 @@
+use crate::mapping::Parsed;
 use once_cell::unsync::Lazy;
 use regex::{Regex, RegexSet};
 use std::path::PathBuf;
@@ -37,6 +38,8 @@ pub fn valid_paths(s: &str) -> anyhow::Result<PathBuf> {
 /// - NUL
 /// - PRN
 /// - AUX
+///
+/// and does not end with a `.` or a space.
 /// If the given string does not contain any of the aforementioned substrings, the function returns true, else returns false.
 ///
 /// This function statically loaded and compiled regular expressions from the [`regex`] crate using the [`once_cell::unsync::Lazy::new()`] function. The regular expressions are compiled only when the function is called and only compile once.
@@ -64,11 +67,129 @@ pub fn valid_name(name: &str) -> bool {
     !(name.is_empty()
         || regexes.is_match(name)
         || name.contains('\0')
-        || name.chars().nth(name.len() - 2).unwrap() == '.'
-        || name.chars().nth(name.len() - 2).unwrap() == ' ')
+        || name.ends_with('.')
+        || name.ends_with(' '))
+}
+
+/// Command typed at a prompt to move to another prompt instead of answering it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Navigation {
+    /// Go back to the previous prompt. Typed as `:back`.
+    Back,
+    /// Skip the current input directory without renaming any of its files. Typed as `:skip`.
+    Skip,
+    /// Discard every answer and start over from the first input directory. Typed as `:restart`.
+    Restart,
+}
+
+/// Given the answer to a prompt, will return the [`Navigation`] command it contains, if any.
+///
+/// The commands are `:back`, `:skip` and `:restart`, and are case insensitive. Any other answer returns [`None`] and should be handled as a normal answer to the prompt.
+///
+/// # Example
+/// ```
+/// assert_eq!(parse_navigation(":back"), Some(Navigation::Back));
+/// assert_eq!(parse_navigation("Show"), None);
+/// ```
+pub fn parse_navigation(answer: &str) -> Option<Navigation> {
+    match answer.trim().to_ascii_lowercase().as_str() {
+        ":back" => Some(Navigation::Back),
+        ":skip" => Some(Navigation::Skip),
+        ":restart" => Some(Navigation::Restart),
+        _ => None,
+    }
+}
+
+/// A change to a single row of the preview table, typed at the edit step before executing the changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// Give the row a new episode number. Typed as `episode <row> <number>`.
+    Episode(usize, u32),
+    /// Move the row to another season. Typed as `season <row> <number>`.
+    Season(usize, u32),
+    /// Do not rename the file of the row. Typed as `exclude <row>`.
+    Exclude(usize),
+    /// Use a different file name for the renamed file of the row. Typed as `name <row> <file name>`.
+    Name(usize, String),
+}
+
+/// Given an answer typed at the edit step, will return the [`Edit`] it describes, or [`None`] if it is not a valid edit.
+///
+/// The row is the number shown in the first column of the preview table. For `name`, the file name is everything after the row number and must be accepted by [`valid_name()`].
+///
+/// # Example
+/// ```
+/// assert_eq!(parse_edit("season 3 2"), Some(Edit::Season(3, 2)));
+/// assert_eq!(parse_edit("name 0 Pilot"), Some(Edit::Name(0, "Pilot".to_owned())));
+/// assert_eq!(parse_edit("exclude"), None);
+/// ```
+pub fn parse_edit(answer: &str) -> Option<Edit> {
+    let mut words = answer.split_whitespace();
+    let command = words.next()?.to_ascii_lowercase();
+    let row = words.next()?.parse::<usize>().ok()?;
+    let rest = words.collect::<Vec<_>>();
+    match (command.as_str(), rest.as_slice()) {
+        ("episode", [number]) => Some(Edit::Episode(row, number.parse().ok()?)),
+        ("season", [number]) => Some(Edit::Season(row, number.parse().ok()?)),
+        ("exclude", []) => Some(Edit::Exclude(row)),
+        ("name", [_, ..]) => {
+            let name = rest.join(" ");
+            match valid_name(&name) {
+                true => Some(Edit::Name(row, name)),
+                false => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Reason why a token given to [`parse_range()`] was left out of the selection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IgnoreReason {
+    /// The token refers to a file number that is not in the list. eg.(4 when there are 4 files)
+    OutOfRange,
+    /// The token is a dual ended range whose left end is greater than its right end. eg.(5-2)
+    ReversedRange,
+    /// The token does not match any of the accepted formats. eg.(a, 1..3)
+    Unrecognized,
+    /// The file number was already selected by an earlier token. eg.(2 in 0-3 2)
+    Duplicate,
+}
+
+impl std::fmt::Display for IgnoreReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IgnoreReason::OutOfRange => write!(f, "not in the list of files"),
+            IgnoreReason::ReversedRange => write!(f, "range start is greater than its end"),
+            IgnoreReason::Unrecognized => write!(f, "not a number or range"),
+            IgnoreReason::Duplicate => write!(f, "already selected"),
+        }
+    }
 }
 
-/// Given a string and the amount of files in a folder, will return a [`Result<Vec<usize>>`] containing the indexes of the selected files in either:
+/// A token from the user's selection that was not used, along with why it was left out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IgnoredToken {
+    /// The part of the selection that was ignored, as typed by the user.
+    pub token: String,
+    /// Why the token was ignored.
+    pub reason: IgnoreReason,
+}
+
+/// The result of parsing a user's file selection with [`parse_range()`].
+///
+/// Holds the indexes of the selected files as well as every token that was left out, so that the user can be told about them instead of silently renaming fewer files than intended.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Selection {
+    /// Indexes of the selected files in the order they were typed, without duplicates.
+    pub indexes: Vec<usize>,
+    /// Tokens that were not used for the selection.
+    pub ignored: Vec<IgnoredToken>,
+    /// What each selected file was parsed to, in the same order as the indexes, when the files were selected with a [`crate::mapping::Mapping`] instead of a range. Empty otherwise.
+    pub parsed: Vec<Parsed>,
+}
+
+/// Given a string and the amount of files in a folder, will return a [`Result<Selection>`] containing the indexes of the selected files in either:
 /// - A dual ended range. eg.(0-3)
 /// - A left ended range. eg.(0-)
 /// - A right ended range. eg.(-5)
@@ -77,16 +198,25 @@ pub fn valid_name(name: &str) -> bool {
 ///
 /// The input string, if it has multiple ranges, need to be space separated. The amount_files needs to be the length of the vector of the files of which the user has selected.
 ///
+/// Any token that is out of range, is a reversed range or is not in one of the formats above is not selected and is instead recorded in [`Selection::ignored`] with its [`IgnoreReason`].
+/// For comma separated values, only the values that are out of range are ignored. Numbers too large to be parsed are treated as out of range.
+///
+/// The indexes are kept in the order they were typed, so `3 1 2` returns `[3,1,2]`. A file number that was already selected by an earlier token is only kept once and is reported as a [`IgnoreReason::Duplicate`].
+///
 /// # Panics
-/// Under normal circumstances the function should not panic but if the regular expressions are modified, it can panic due to either the regular expressions failing to compile or a parse of a string to a usize fails due to a change in the regular expressions.
+/// Under normal circumstances the function should not panic but if the regular expressions are modified, it can panic due to the regular expressions failing to compile.
 ///
 /// # Example
 /// ```
-/// let result = parse_range(6,"0 1-2 3,4 5")
-/// assert_eq!(result, vec![0,1,2,3,4,5]);
+/// let result = parse_range(6,"0 1-2 3,4 5 7 2")
+/// assert_eq!(result.indexes, vec![0,1,2,3,4,5]);
+/// assert_eq!(result.ignored, vec![
+///     IgnoredToken { token: "7".to_owned(), reason: IgnoreReason::OutOfRange },
+///     IgnoredToken { token: "2".to_owned(), reason: IgnoreReason::Duplicate },
+/// ]);
 /// ```
-pub fn parse_range(amount_files: usize, range: String) -> anyhow::Result<Vec<usize>> {
-    let mut file_numbers: Vec<usize> = Vec::new();
+pub fn parse_range(amount_files: usize, range: String) -> anyhow::Result<Selection> {
+    let mut selection = Selection::default();
     let dualendedrange: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^\d+-\d+$"#).unwrap());
     let leftendedrange: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^\d+-$"#).unwrap());
     let rightendedrange: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^-\d+$"#).unwrap());
@@ -96,6 +226,13 @@ pub fn parse_range(amount_files: usize, range: String) -> anyhow::Result<Vec<usi
         .split_ascii_whitespace()
         .map(|x| x.to_owned())
         .collect::<Vec<_>>();
+    let mut ignore = |token: &str, reason: IgnoreReason| {
+        selection.ignored.push(IgnoredToken {
+            token: token.to_owned(),
+            reason,
+        })
+    };
+    let mut file_numbers: Vec<usize> = Vec::new();
     if range.is_empty() {
         for num in 0..amount_files {
             file_numbers.push(num);
@@ -104,48 +241,358 @@ pub fn parse_range(amount_files: usize, range: String) -> anyhow::Result<Vec<usi
         for r in ranges {
             if dualendedrange.is_match(&r) {
                 let nums = r.split('-').collect::<Vec<&str>>();
-                let left: usize = nums.first().unwrap().parse()?;
-                let right: usize = nums.get(1).unwrap().parse()?;
-                if left < amount_files && right < amount_files && left <= right {
+                let left: usize = nums.first().unwrap().parse().unwrap_or(usize::MAX);
+                let right: usize = nums.get(1).unwrap().parse().unwrap_or(usize::MAX);
+                if left > right {
+                    ignore(&r, IgnoreReason::ReversedRange);
+                } else if left < amount_files && right < amount_files {
                     for num in left..(right + 1) {
                         file_numbers.push(num);
                     }
+                } else {
+                    ignore(&r, IgnoreReason::OutOfRange);
                 }
             } else if leftendedrange.is_match(&r) {
                 let nums = r.split('-').collect::<Vec<&str>>();
-                let left: usize = nums.first().unwrap().parse()?;
+                let left: usize = nums.first().unwrap().parse().unwrap_or(usize::MAX);
                 if left < amount_files {
                     for num in left..amount_files {
                         file_numbers.push(num);
                     }
+                } else {
+                    ignore(&r, IgnoreReason::OutOfRange);
                 }
             } else if rightendedrange.is_match(&r) {
                 let nums = r.split('-').collect::<Vec<&str>>();
-                let right: usize = nums.get(1).unwrap().parse()?;
+                let right: usize = nums.get(1).unwrap().parse().unwrap_or(usize::MAX);
                 if right < amount_files {
                     for num in 0..(right + 1) {
                         file_numbers.push(num);
                     }
+                } else {
+                    ignore(&r, IgnoreReason::OutOfRange);
                 }
             } else if csv.is_match(&r) {
-                let nums = r
-                    .split(',')
-                    .collect::<Vec<_>>()
-                    .into_iter()
-                    .map(|x| x.parse().unwrap())
-                    .collect::<Vec<usize>>();
-                for num in nums {
+                for value in r.split(',') {
+                    let num: usize = value.parse().unwrap_or(usize::MAX);
                     if num < amount_files {
                         file_numbers.push(num);
+                    } else {
+                        ignore(value, IgnoreReason::OutOfRange);
                     }
                 }
             } else if single.is_match(&r) {
-                let num: usize = r.parse().unwrap();
+                let num: usize = r.parse().unwrap_or(usize::MAX);
                 if num < amount_files {
                     file_numbers.push(num);
+                } else {
+                    ignore(&r, IgnoreReason::OutOfRange);
                 }
+            } else {
+                ignore(&r, IgnoreReason::Unrecognized);
             }
         }
     }
-    Ok(file_numbers)
+    for num in file_numbers {
+        if selection.indexes.contains(&num) {
+            selection.ignored.push(IgnoredToken {
+                token: num.to_string(),
+                reason: IgnoreReason::Duplicate,
+            });
+        } else {
+            selection.indexes.push(num);
+        }
+    }
+    Ok(selection)
+}
+
+/// Owner and group given with the --owner flag, as numeric IDs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Owner {
+    /// ID of the user that will own the files, or [`None`] to keep the current one.
+    pub uid: Option<u32>,
+    /// ID of the group that will own the files, or [`None`] to keep the current one.
+    pub gid: Option<u32>,
+}
+
+/// Parses the value of the --owner flag, which is a user, a user and a group in the form `user:group`, or only a group in the form `:group`.
+/// Users and groups can be given by name or by numeric ID.
+///
+/// # Errors
+/// If the user or group does not exist, or if ownership cannot be changed on this platform.
+///
+/// # Example
+/// ```
+/// let owner = parse_owner("plex:plex")?;
+/// assert_eq!(parse_owner(":1000")?, Owner { uid: None, gid: Some(1000) });
+/// ```
+#[cfg(unix)]
+pub fn parse_owner(s: &str) -> Result<Owner, String> {
+    use nix::unistd::{Group, User};
+    let (user, group) = s.split_once(':').unwrap_or((s, ""));
+    let uid = match user {
+        "" => None,
+        user => match (user.parse::<u32>(), User::from_name(user)) {
+            (Ok(uid), _) => Some(uid),
+            (_, Ok(Some(user))) => Some(user.uid.as_raw()),
+            _ => return Err(format!("there is no user named {user}")),
+        },
+    };
+    let gid = match group {
+        "" => None,
+        group => match (group.parse::<u32>(), Group::from_name(group)) {
+            (Ok(gid), _) => Some(gid),
+            (_, Ok(Some(group))) => Some(group.gid.as_raw()),
+            _ => return Err(format!("there is no group named {group}")),
+        },
+    };
+    match (uid, gid) {
+        (None, None) => Err("a user, a group or both must be given".to_owned()),
+        _ => Ok(Owner { uid, gid }),
+    }
+}
+
+/// Parses the value of the --owner flag.
+///
+/// # Errors
+/// Always, since ownership cannot be changed on this platform.
+#[cfg(not(unix))]
+pub fn parse_owner(_s: &str) -> Result<Owner, String> {
+    Err("changing the owner of files is not supported on this platform".to_owned())
+}
+
+/// Parses the value of the --mode flag, which is the permission bits of a file as an octal number, such as `644` or `0640`.
+///
+/// # Errors
+/// If the value is not an octal number up to `7777`.
+///
+/// # Example
+/// ```
+/// assert_eq!(parse_mode("644"), Ok(0o644));
+/// ```
+pub fn parse_mode(s: &str) -> Result<u32, String> {
+    match u32::from_str_radix(s, 8) {
+        Ok(mode) if mode <= 0o7777 => Ok(mode),
+        _ => Err(format!("{s} is not an octal file mode such as 644")),
+    }
+}
+
+/// Parses the value of the --template flag, which is the file name given to every episode without its extension, such as `{series} - S{season}E{episode}`.
+///
+/// The template can use the `{series}`, `{season}`, `{episode}` and `{title}` placeholders, and must use `{episode}` so that every episode of a season gets a different name.
+/// The rest of the template must be valid in a file name, as checked by [`valid_name()`].
+///
+/// # Errors
+/// If the template has an unknown placeholder, does not use `{episode}` or has characters that are not valid in a file name.
+///
+/// # Example
+/// ```
+/// assert!(parse_template("{series} S{season}E{episode}").is_ok());
+/// assert!(parse_template("{series} - S{season}E{episode} - {title}").is_ok());
+/// assert!(parse_template("{series} S{season}").is_err());
+/// ```
+pub fn parse_template(s: &str) -> Result<String, String> {
+    let placeholder = Regex::new(r"\{([^{}]*)\}").unwrap();
+    if let Some(unknown) = placeholder
+        .captures_iter(s)
+        .map(|x| x[1].to_owned())
+        .find(|x| !["series", "season", "episode", "title"].contains(&x.as_str()))
+    {
+        return Err(format!(
+            "{{{unknown}}} is not a placeholder, use {{series}}, {{season}}, {{episode}} or {{title}}"
+        ));
+    }
+    if !s.contains("{episode}") {
+        return Err("the template must use the {episode} placeholder".to_owned());
+    }
+    let literal = placeholder.replace_all(s, "x");
+    match literal.contains(['{', '}']) || !valid_name(&literal) {
+        true => Err(format!(
+            "{s} has characters that are not valid in a file name"
+        )),
+        false => Ok(s.to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ignored(token: &str, reason: IgnoreReason) -> IgnoredToken {
+        IgnoredToken {
+            token: token.to_owned(),
+            reason,
+        }
+    }
+
+    #[test]
+    fn parse_range_selects_every_format() {
+        let selection = parse_range(10, "2-3 8- -1 4,5 6".to_owned()).unwrap();
+        assert_eq!(selection.indexes, vec![2, 3, 8, 9, 0, 1, 4, 5, 6]);
+        assert!(selection.ignored.is_empty());
+    }
+
+    #[test]
+    fn parse_range_selects_every_file_when_empty() {
+        let selection = parse_range(3, String::new()).unwrap();
+        assert_eq!(selection.indexes, vec![0, 1, 2]);
+        assert!(selection.ignored.is_empty());
+    }
+
+    #[test]
+    fn parse_range_ignores_reversed_ranges() {
+        let selection = parse_range(6, "5-2 0".to_owned()).unwrap();
+        assert_eq!(selection.indexes, vec![0]);
+        assert_eq!(
+            selection.ignored,
+            vec![ignored("5-2", IgnoreReason::ReversedRange)]
+        );
+    }
+
+    #[test]
+    fn parse_range_ignores_out_of_range_tokens() {
+        let selection = parse_range(4, "4 2-4 5- -9 1,7 3".to_owned()).unwrap();
+        assert_eq!(selection.indexes, vec![1, 3]);
+        assert_eq!(
+            selection.ignored,
+            vec![
+                ignored("4", IgnoreReason::OutOfRange),
+                ignored("2-4", IgnoreReason::OutOfRange),
+                ignored("5-", IgnoreReason::OutOfRange),
+                ignored("-9", IgnoreReason::OutOfRange),
+                ignored("7", IgnoreReason::OutOfRange),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_range_treats_huge_numbers_as_out_of_range() {
+        let selection = parse_range(2, "99999999999999999999999".to_owned()).unwrap();
+        assert!(selection.indexes.is_empty());
+        assert_eq!(
+            selection.ignored,
+            vec![ignored("99999999999999999999999", IgnoreReason::OutOfRange)]
+        );
+    }
+
+    #[test]
+    fn parse_range_ignores_unrecognized_tokens() {
+        let selection = parse_range(5, "a 1..3 2".to_owned()).unwrap();
+        assert_eq!(selection.indexes, vec![2]);
+        assert_eq!(
+            selection.ignored,
+            vec![
+                ignored("a", IgnoreReason::Unrecognized),
+                ignored("1..3", IgnoreReason::Unrecognized),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_range_keeps_the_typed_order_without_duplicates() {
+        let selection = parse_range(6, "3 1 2 0-3 2".to_owned()).unwrap();
+        assert_eq!(selection.indexes, vec![3, 1, 2, 0]);
+        assert_eq!(
+            selection.ignored,
+            vec![
+                ignored("1", IgnoreReason::Duplicate),
+                ignored("2", IgnoreReason::Duplicate),
+                ignored("3", IgnoreReason::Duplicate),
+                ignored("2", IgnoreReason::Duplicate),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_navigation_finds_commands() {
+        assert_eq!(parse_navigation(":back"), Some(Navigation::Back));
+        assert_eq!(parse_navigation(" :SKIP "), Some(Navigation::Skip));
+        assert_eq!(parse_navigation(":Restart"), Some(Navigation::Restart));
+    }
+
+    #[test]
+    fn parse_navigation_ignores_answers() {
+        assert_eq!(parse_navigation("Show"), None);
+        assert_eq!(parse_navigation("back"), None);
+        assert_eq!(parse_navigation(":back now"), None);
+        assert_eq!(parse_navigation(""), None);
+    }
+
+    #[test]
+    fn parse_edit_reads_every_edit() {
+        assert_eq!(parse_edit("episode 1 4"), Some(Edit::Episode(1, 4)));
+        assert_eq!(parse_edit("Season 3 2"), Some(Edit::Season(3, 2)));
+        assert_eq!(parse_edit("exclude 0"), Some(Edit::Exclude(0)));
+        assert_eq!(
+            parse_edit("name 2 The  Pilot"),
+            Some(Edit::Name(2, "The Pilot".to_owned()))
+        );
+    }
+
+    #[test]
+    fn parse_edit_rejects_invalid_edits() {
+        assert_eq!(parse_edit(""), None);
+        assert_eq!(parse_edit("exclude"), None);
+        assert_eq!(parse_edit("exclude 1 2"), None);
+        assert_eq!(parse_edit("episode one 2"), None);
+        assert_eq!(parse_edit("episode 1"), None);
+        assert_eq!(parse_edit("season 1 -2"), None);
+        assert_eq!(parse_edit("name 1"), None);
+        assert_eq!(parse_edit("name 1 a/b"), None);
+        assert_eq!(parse_edit("rename 1 2"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parse_owner_reads_ids_and_names() {
+        let owner = |uid, gid| Ok(Owner { uid, gid });
+        assert_eq!(parse_owner("1000:1001"), owner(Some(1000), Some(1001)));
+        assert_eq!(parse_owner("1000"), owner(Some(1000), None));
+        assert_eq!(parse_owner(":1001"), owner(None, Some(1001)));
+        assert_eq!(parse_owner("root:0"), owner(Some(0), Some(0)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parse_owner_rejects_unknown_owners() {
+        assert!(parse_owner("").is_err());
+        assert!(parse_owner(":").is_err());
+        assert!(parse_owner("no-such-user-rplexfmtr").is_err());
+        assert!(parse_owner(":no-such-group-rplexfmtr").is_err());
+    }
+
+    #[test]
+    fn parse_mode_reads_octal_modes() {
+        assert_eq!(parse_mode("644"), Ok(0o644));
+        assert_eq!(parse_mode("0640"), Ok(0o640));
+        assert_eq!(parse_mode("7777"), Ok(0o7777));
+    }
+
+    #[test]
+    fn parse_mode_rejects_invalid_modes() {
+        assert!(parse_mode("").is_err());
+        assert!(parse_mode("8").is_err());
+        assert!(parse_mode("rw-r--r--").is_err());
+        assert!(parse_mode("17777").is_err());
+    }
+
+    #[test]
+    fn parse_template_accepts_placeholders() {
+        for template in [
+            "{series} S{season}E{episode}",
+            "{series} - S{season}E{episode} - {title}",
+            "{episode}",
+        ] {
+            assert_eq!(parse_template(template), Ok(template.to_owned()));
+        }
+    }
+
+    #[test]
+    fn parse_template_rejects_invalid_templates() {
+        assert!(parse_template("{series} S{season}").is_err());
+        assert!(parse_template("{series} {name} {episode}").is_err());
+        assert!(parse_template("{series}/{episode}").is_err());
+        assert!(parse_template("{series {episode}").is_err());
+        assert!(parse_template("{series} {episode}.").is_err());
+    }
 }
@@ -0,0 +1,471 @@
+use crate::cleanup::{clean_up, plan_cleanup};
+use crate::database::{delete_all_episodes, insert_episode, select_plan, Episode};
+use crate::files::{
+    episode_path, get_show_names, handle_interrupts, interrupted, move_files, Cli, Outcome,
+};
+use crate::library::{library_of, Library};
+use crate::plan::{check_progress, discard_progress};
+use crate::preflight::preflight;
+use crate::rules::rule_episode;
+use crate::scan::guess_episode;
+use crate::validate::valid_name;
+use colored::*;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// How long [`watch()`] waits for file system events before checking whether the pending files are ready.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A drop folder watched by `rplexfmtr watch`, given on the command line or as a `[[watch]]` table of the config file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchFolder {
+    /// The canonical path of the folder new downloads are saved to. Files in its subfolders are watched as well.
+    pub path: PathBuf,
+    /// The name of the [`Library`] the files are renamed to, the only library when [`None`].
+    pub library: Option<String>,
+    /// The series every file of the folder belongs to, guessed from the name of each file when [`None`].
+    pub series: Option<String>,
+}
+
+/// Priority of a message logged by [`log()`], following the syslog levels understood by the systemd journal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Priority {
+    /// A file could not be renamed.
+    Error = 3,
+    /// A file was left in place, such as when it could not be matched to a show.
+    Warning = 4,
+    /// A file was found or renamed.
+    Info = 6,
+}
+
+/// Logs a message of [`watch()`] to standard error.
+///
+/// When standard error is connected to the systemd journal, which sets the `JOURNAL_STREAM` environment variable, the message is prefixed with its [`Priority`] as `<6>`, so that the journal records it with that priority.
+/// Else the message is colored by its priority instead.
+///
+/// # Example
+/// ```
+/// log(Priority::Info, "Watching /home/user/downloads.");
+/// ```
+pub fn log(priority: Priority, message: &str) {
+    match std::env::var_os("JOURNAL_STREAM").is_some() {
+        true => eprintln!("<{}>{message}", priority as u8),
+        false => match priority {
+            Priority::Error => eprintln!("{}", message.red()),
+            Priority::Warning => eprintln!("{}", message.yellow()),
+            Priority::Info => eprintln!("{message}"),
+        },
+    }
+}
+
+/// Returns every file that any process has open for writing, which means it is still being downloaded.
+///
+/// The open files of every process are read from `/proc` once, so only the processes the user is allowed to inspect are checked.
+/// The paths are absolute with every symbolic link resolved, so they can only be compared with the paths under the canonical paths of the [`WatchFolder`]s.
+#[cfg(target_os = "linux")]
+pub fn open_for_writing() -> HashSet<PathBuf> {
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return HashSet::new();
+    };
+    let mut files = HashSet::new();
+    for process in processes.flatten() {
+        let Ok(descriptors) = std::fs::read_dir(process.path().join("fd")) else {
+            continue;
+        };
+        for descriptor in descriptors.flatten() {
+            let Ok(path) = std::fs::read_link(descriptor.path()) else {
+                continue;
+            };
+            let info = process.path().join("fdinfo").join(descriptor.file_name());
+            let writing = std::fs::read_to_string(info)
+                .ok()
+                .and_then(|x| {
+                    x.lines()
+                        .find_map(|x| x.strip_prefix("flags:"))
+                        .and_then(|x| u32::from_str_radix(x.trim(), 8).ok())
+                })
+                .is_some_and(|flags| flags & 0o3 != 0);
+            if writing {
+                files.insert(path);
+            }
+        }
+    }
+    files
+}
+
+/// Returns every file that any process has open for writing, which cannot be told on this platform, so only the size of the files is checked by [`watch()`].
+#[cfg(not(target_os = "linux"))]
+pub fn open_for_writing() -> HashSet<PathBuf> {
+    HashSet::new()
+}
+
+/// Returns true if the file has one of the given extensions, or if no extensions are given, like the filter of [`crate::files::get_files()`].
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    extensions.is_empty()
+        || path.extension().is_some_and(|e| {
+            extensions
+                .iter()
+                .any(|x| e.eq_ignore_ascii_case(x.trim_start_matches('.')))
+        })
+}
+
+/// Returns every file under the given path, including the ones in its subfolders, or the path itself if it is a file.
+fn walk(path: &Path) -> Vec<PathBuf> {
+    match std::fs::read_dir(path) {
+        Ok(entries) => entries.flatten().flat_map(|x| walk(&x.path())).collect(),
+        Err(_) if path.is_file() => vec![path.to_path_buf()],
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Returns the name of a series folder already in the library that only differs from the given name in case and punctuation, or the given name if there is none.
+///
+/// This way `the.office` and `The Office` are both renamed to the existing `The Office` folder.
+fn existing_series(library: &Library, series_name: &str) -> String {
+    let key = |name: &str| {
+        name.chars()
+            .filter(|x| x.is_alphanumeric())
+            .collect::<String>()
+            .to_lowercase()
+    };
+    get_show_names(&library.path)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|x| key(x) == key(series_name))
+        .unwrap_or_else(|| series_name.to_owned())
+}
+
+/// Matches a file of a drop folder to the episode it will be renamed to, or returns [`None`] if it cannot be matched.
+///
+/// The file is first matched with the rules of the rules file by [`rule_episode()`], in which case it is renamed to the library of the rule, if it names one, else to the given library.
+/// Else, the season and episode are guessed from the name of the file with [`guess_episode()`]. The series is the one of the [`WatchFolder`] if it has one, else the one guessed from the name, using the name of an existing series folder of the library when it only differs in case and punctuation.
+///
+/// # Example
+/// ```
+/// let episode = match_file(&args, &folder, 0, Path::new("/home/user/downloads/The.Show.S01E02.mkv"));
+/// ```
+pub fn match_file(
+    args: &Cli,
+    folder: &WatchFolder,
+    library: usize,
+    file: &Path,
+) -> Option<Episode> {
+    if let Some(episode) = rule_episode(args, Some(library), file) {
+        return Some(episode);
+    }
+    let library = &args.libraries[library];
+    let guess = guess_episode(file.file_name()?.to_str()?)?;
+    let series_name = existing_series(
+        library,
+        folder.series.as_deref().unwrap_or(&guess.series_name),
+    );
+    if !valid_name(&series_name) {
+        return None;
+    }
+    Some(Episode {
+        new_path: episode_path(
+            library,
+            &series_name,
+            guess.season,
+            guess.episode,
+            None,
+            file,
+        )
+        .to_str()?
+        .to_owned(),
+        old_path: file.to_str()?.to_owned(),
+        series_name,
+        season: guess.season,
+        episode: guess.episode,
+        title: None,
+    })
+}
+
+/// Returns the index of the library the files of the given drop folder are renamed to.
+///
+/// # Errors
+/// If the folder names a library that is not in the config file, or names none while there is more than one library.
+fn folder_library(libraries: &[Library], folder: &WatchFolder) -> anyhow::Result<usize> {
+    match &folder.library {
+        Some(name) => libraries
+            .iter()
+            .position(|x| x.name.as_ref().is_some_and(|x| x.eq_ignore_ascii_case(name)))
+            .ok_or_else(|| anyhow::anyhow!("There is no library named {name} in the config file.")),
+        None => match libraries.len() {
+            1 => Ok(0),
+            0 => anyhow::bail!("Give the output path with -o, or name libraries in the config file."),
+            _ => anyhow::bail!(
+                "Give the library the files of {} are renamed to with -l, or with the library setting of its [[watch]] table.",
+                folder.path.display()
+            ),
+        },
+    }
+}
+
+/// A file of a drop folder that is waiting to be fully written.
+struct Pending {
+    /// Index of the [`WatchFolder`] the file is in.
+    folder: usize,
+    /// Size of the file when it was last checked.
+    size: u64,
+    /// When the size of the file last changed.
+    changed: Instant,
+}
+
+/// Renames the given files of the drop folders with the same pipeline as the prompts, inserting their episodes with [`insert_episode()`] and transferring them with [`move_files()`].
+///
+/// Every action is logged with [`log()`]. The files that are left in place, either because they could not be matched to a show or because they could not be renamed, are returned so that they are not tried again until they change. For the same reason, the progress kept by [`move_files()`] is discarded with [`discard_progress()`].
+/// If [`move_files()`] fails or is interrupted, the error is logged, the files of the batch that were not renamed are returned as left in place and the input folders are not cleaned up.
+async fn rename_ready(
+    db: &sqlx::SqlitePool,
+    args: &Cli,
+    folders: &[WatchFolder],
+    routes: &[usize],
+    files: Vec<(PathBuf, usize)>,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut left = Vec::new();
+    delete_all_episodes(db).await?;
+    for (file, folder) in files {
+        let Some(episode) = match_file(args, &folders[folder], routes[folder], &file) else {
+            log(
+                Priority::Warning,
+                &format!(
+                    "Could not match {} to a show, it was left in place.",
+                    file.display()
+                ),
+            );
+            left.push(file);
+            continue;
+        };
+        let inserted = insert_episode(
+            db,
+            &episode.series_name,
+            episode.season,
+            episode.episode,
+            episode.title.as_deref(),
+            PathBuf::from(&episode.old_path),
+            PathBuf::from(&episode.new_path),
+        )
+        .await;
+        if inserted.is_err() {
+            log(
+                Priority::Warning,
+                &format!(
+                    "{} is renamed to the same episode as another file, it was left in place.",
+                    file.display()
+                ),
+            );
+            left.push(file);
+            continue;
+        }
+        log(
+            Priority::Info,
+            &format!(
+                "Matched {} to {} season {} episode {} in {}.",
+                file.display(),
+                episode.series_name,
+                episode.season,
+                episode.episode,
+                library_of(&args.libraries, Path::new(&episode.new_path)).unwrap()
+            ),
+        );
+    }
+    let episodes = select_plan(db).await?;
+    if episodes.is_empty() {
+        return Ok(left);
+    }
+    if !args.keep_going {
+        let preflight = preflight(&episodes, args.transfer_mode());
+        if !preflight.problems.is_empty() {
+            for problem in &preflight.problems {
+                log(
+                    Priority::Error,
+                    &format!("{problem}, no files were renamed."),
+                );
+            }
+            left.extend(episodes.iter().map(|x| PathBuf::from(&x.old_path)));
+            return Ok(left);
+        }
+    }
+    let (failures, stopped) = match move_files(db, args).await {
+        Ok(Outcome::Finished(failures)) => (failures, false),
+        Ok(Outcome::Interrupted(_)) => {
+            log(
+                Priority::Warning,
+                "Interrupted while renaming, the files that were not renamed were left in place.",
+            );
+            (Vec::new(), true)
+        }
+        Err(e) => {
+            log(
+                Priority::Error,
+                &format!("{e:#}. The files that were not renamed were left in place."),
+            );
+            (Vec::new(), true)
+        }
+    };
+    discard_progress()?;
+    for failure in &failures {
+        log(
+            Priority::Error,
+            &format!(
+                "Could not rename {} to {}: {}",
+                failure.old_path, failure.new_path, failure.error
+            ),
+        );
+    }
+    for episode in &episodes {
+        match Path::new(&episode.new_path).exists() {
+            true => log(
+                Priority::Info,
+                &format!("Renamed {} to {}.", episode.old_path, episode.new_path),
+            ),
+            false => left.push(PathBuf::from(&episode.old_path)),
+        }
+    }
+    if args.cleanup && !stopped {
+        let mut dirs = Vec::new();
+        for episode in &episodes {
+            let dir = Path::new(&episode.old_path).parent().unwrap().to_path_buf();
+            if !folders.iter().any(|x| x.path == dir) && !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+        match clean_up(&plan_cleanup(&dirs, &episodes, args), args) {
+            Ok(removed) => {
+                for dir in removed {
+                    log(Priority::Info, &format!("Removed {}.", dir.display()));
+                }
+            }
+            Err(e) => log(Priority::Error, &format!("{e:#}")),
+        }
+    }
+    Ok(left)
+}
+
+/// Watches the given drop folders and renames the files saved to them, until SIGINT or SIGTERM is received.
+///
+/// The folders and their subfolders are watched for new files with [`notify`], which uses inotify on Linux. The files already in the folders are picked up when watching starts.
+/// A file is only renamed once it is fully written, which is when it is not empty, its size has not changed for the given amount of time and no process has it open for writing, as told by [`open_for_writing()`] once for all the files that settled since the last check.
+/// Only the files with the extensions of the --extensions flag are renamed, if it was given.\
+///
+/// The ready files are then matched to a show with [`match_file()`] and renamed to the [`Library`] of their [`WatchFolder`] or of the rule that matched them, using [`insert_episode()`] and [`move_files()`] along with the flags given, such as --transfer, --keep-going or --cleanup.
+/// Every action is logged with [`log()`], so that it ends up in the systemd journal when run as a service. Files that cannot be matched or renamed are left in place and only tried again once they change.
+/// Like a rename, watching does not start while the last run has files left over, as checked with [`check_progress()`].
+///
+/// # Errors
+/// If a folder names a library that does not exist or the folders cannot be watched.
+///
+/// # Example
+/// ```
+/// watch(&db, &args, &args.watch_folders, Duration::from_secs(30)).await?;
+/// ```
+pub async fn watch(
+    db: &sqlx::SqlitePool,
+    args: &Cli,
+    folders: &[WatchFolder],
+    settle: Duration,
+) -> anyhow::Result<()> {
+    if folders.is_empty() {
+        anyhow::bail!("Give the folders to watch, or add [[watch]] tables to the config file.");
+    }
+    check_progress()?;
+    let routes = folders
+        .iter()
+        .map(|x| folder_library(&args.libraries, x))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    handle_interrupts();
+    let (sender, receiver) = channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    let mut pending: HashMap<PathBuf, Pending> = HashMap::new();
+    let mut left: HashSet<PathBuf> = HashSet::new();
+    let mut checked = Instant::now();
+    let add = |pending: &mut HashMap<PathBuf, Pending>, path: &Path, folder: usize| {
+        for file in walk(path) {
+            if has_extension(&file, &args.extensions) {
+                pending.insert(
+                    file,
+                    Pending {
+                        folder,
+                        size: 0,
+                        changed: Instant::now(),
+                    },
+                );
+            }
+        }
+    };
+    for (index, folder) in folders.iter().enumerate() {
+        watcher.watch(&folder.path, RecursiveMode::Recursive)?;
+        add(&mut pending, &folder.path, index);
+        log(
+            Priority::Info,
+            &format!(
+                "Watching {} for {}.",
+                folder.path.display(),
+                args.libraries[routes[index]]
+            ),
+        );
+    }
+    loop {
+        if interrupted() {
+            log(Priority::Info, "Stopped watching.");
+            return Ok(());
+        }
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        let Some(folder) = folders.iter().position(|x| path.starts_with(&x.path))
+                        else {
+                            continue;
+                        };
+                        left.retain(|x| !x.starts_with(&path));
+                        add(&mut pending, &path, folder);
+                    }
+                }
+            }
+            Ok(Err(e)) => log(
+                Priority::Warning,
+                &format!("Could not watch the folders: {e}"),
+            ),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                anyhow::bail!("Stopped receiving file system events.")
+            }
+        }
+        if checked.elapsed() < POLL_INTERVAL {
+            continue;
+        }
+        checked = Instant::now();
+        let mut settled = Vec::new();
+        pending.retain(|path, file| {
+            let Ok(metadata) = std::fs::metadata(path) else {
+                return false;
+            };
+            if metadata.len() != file.size {
+                file.size = metadata.len();
+                file.changed = Instant::now();
+            } else if file.size > 0 && file.changed.elapsed() >= settle {
+                settled.push(path.clone());
+            }
+            true
+        });
+        let mut ready = Vec::new();
+        if !settled.is_empty() {
+            let writing = open_for_writing();
+            for path in settled.into_iter().filter(|x| !writing.contains(x)) {
+                let file = pending.remove(&path).unwrap();
+                if !left.contains(&path) {
+                    ready.push((path, file.folder));
+                }
+            }
+        }
+        if !ready.is_empty() {
+            ready.sort_by(|a, b| natord::compare(&a.0.to_string_lossy(), &b.0.to_string_lossy()));
+            left.extend(rename_ready(db, args, folders, &routes, ready).await?);
+        }
+    }
+}